@@ -23,6 +23,7 @@
 
 #if !TARGET_OS_IPHONE && !TARGET_OS_SIMULATOR && !TARGET_OS_EMBEDDED
 @interface WRVTextLayer : CAOpenGLLayer {
+    CGLContextObj _cglContext;
 #else
 @interface WRVTextLayer : CAEAGLLayer {
     EAGLContext *_glContext;
@@ -50,7 +51,10 @@
 - (void)mouseDown:(NSEvent *)event;
 - (void)mouseDragged:(NSEvent *)event;
 - (void)mouseUp:(NSEvent *)event;
-- (void)mouseMoved:(NSEvent *)event;
+// These return the type of the event the view reported, which is
+// `WRTV_EVENT_RESULT_NONE` if nothing needs to be redrawn.
+- (uint8_t)mouseMoved:(NSEvent *)event;
+- (uint8_t)mouseExited:(NSEvent *)event;
 #else
 - (void)setImage:(UIImage *)image forID:(uint32_t)imageID;
 - (void)attachedToWindow;
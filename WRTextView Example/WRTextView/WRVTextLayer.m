@@ -55,6 +55,20 @@ static const wrtv_mouse_event_kind_t WRMouseEventKindFromNSEvent(NSEvent *event)
     }
     return WRTV_MOUSE_EVENT_KIND_T_LEFT;
 }
+
+static const wrtv_modifiers_t WRModifiersFromNSEvent(NSEvent *event) {
+    NSEventModifierFlags flags = [event modifierFlags];
+    wrtv_modifiers_t modifiers = 0;
+    if (flags & NSEventModifierFlagShift)
+        modifiers |= WRTV_MODIFIERS_SHIFT;
+    if (flags & NSEventModifierFlagControl)
+        modifiers |= WRTV_MODIFIERS_CONTROL;
+    if (flags & NSEventModifierFlagOption)
+        modifiers |= WRTV_MODIFIERS_ALT;
+    if (flags & NSEventModifierFlagCommand)
+        modifiers |= WRTV_MODIFIERS_META;
+    return modifiers;
+}
 #endif
 
 @implementation WRVTextLayer
@@ -132,7 +146,17 @@ static const wrtv_mouse_event_kind_t WRMouseEventKindFromNSEvent(NSEvent *event)
     [super setDelegate:newDelegate];
     
     if (self->_webRenderView != NULL) {
+        // WebRender deletes its GL objects when the view is destroyed, so the context they live
+        // in has to be current.
+#if !TARGET_OS_IPHONE && !TARGET_OS_SIMULATOR && !TARGET_OS_EMBEDDED
+        CGLSetCurrentContext(self->_cglContext);
         wrtv_view_destroy(self->_webRenderView);
+        CGLSetCurrentContext(NULL);
+#else
+        [EAGLContext setCurrentContext:self->_glContext];
+        wrtv_view_destroy(self->_webRenderView);
+        [EAGLContext setCurrentContext:nil];
+#endif
         self->_webRenderView = NULL;
     }
     
@@ -272,6 +296,16 @@ static const wrtv_mouse_event_kind_t WRMouseEventKindFromNSEvent(NSEvent *event)
     NSAssert(pixelFormatCount > 0, @"No pixel formats found!");
     return pixelFormat;
 }
+
+- (CGLContextObj)copyCGLContextForPixelFormat:(CGLPixelFormatObj)pixelFormat {
+    CGLContextObj cglContext = [super copyCGLContextForPixelFormat:pixelFormat];
+
+    // Hold on to the context so that the view can be destroyed in it later.
+    if (self->_cglContext != NULL)
+        CGLReleaseContext(self->_cglContext);
+    self->_cglContext = CGLRetainContext(cglContext);
+    return cglContext;
+}
 #endif
 
 - (CGAffineTransform)_webrenderTransform {
@@ -468,7 +502,11 @@ static const wrtv_mouse_event_kind_t WRMouseEventKindFromNSEvent(NSEvent *event)
 
     NSPoint point = [self _convertEventLocationToTextViewCoordinateSystem:event];
     wrtv_mouse_event_kind_t mouseEventKind = WRMouseEventKindFromNSEvent(event);
-    wrtv_view_mouse_down(self->_webRenderView, (float)point.x, (float)point.y, mouseEventKind);
+    wrtv_event_result_destroy(wrtv_view_mouse_down(self->_webRenderView,
+                                                   (float)point.x,
+                                                   (float)point.y,
+                                                   mouseEventKind,
+                                                   WRModifiersFromNSEvent(event)));
 }
 
 - (void)mouseDragged:(NSEvent *)event {
@@ -476,7 +514,9 @@ static const wrtv_mouse_event_kind_t WRMouseEventKindFromNSEvent(NSEvent *event)
         return;
     
     NSPoint point = [self _convertEventLocationToTextViewCoordinateSystem:event];
-    wrtv_view_mouse_dragged(self->_webRenderView, (float)point.x, (float)point.y);
+    wrtv_event_result_destroy(wrtv_view_mouse_dragged(self->_webRenderView,
+                                                      (float)point.x,
+                                                      (float)point.y));
 }
 
 - (void)mouseUp:(NSEvent *)event {
@@ -488,7 +528,8 @@ static const wrtv_mouse_event_kind_t WRMouseEventKindFromNSEvent(NSEvent *event)
     wrtv_event_result_t *eventResult = wrtv_view_mouse_up(self->_webRenderView,
                                                           (float)point.x,
                                                           (float)point.y,
-                                                          mouseEventKind);
+                                                          mouseEventKind,
+                                                          WRModifiersFromNSEvent(event));
     
     switch (wrtv_event_result_get_type(eventResult)) {
     case WRTV_EVENT_RESULT_OPEN_URL: {
@@ -503,16 +544,24 @@ static const wrtv_mouse_event_kind_t WRMouseEventKindFromNSEvent(NSEvent *event)
         break;
     }
         
-    case WRTV_EVENT_RESULT_NONE:
+    default:
         break;
     }
+
+    wrtv_event_result_destroy(eventResult);
 }
 
-- (void)mouseMoved:(NSEvent *)event {
+- (uint8_t)mouseMoved:(NSEvent *)event {
     if (self->_webRenderView == NULL)
-        return;
+        return WRTV_EVENT_RESULT_NONE;
     
     NSPoint point = [self _convertEventLocationToTextViewCoordinateSystem:event];
+    wrtv_event_result_t *eventResult = wrtv_view_mouse_moved(self->_webRenderView,
+                                                             (float)point.x,
+                                                             (float)point.y);
+    uint8_t eventResultType = wrtv_event_result_get_type(eventResult);
+    wrtv_event_result_destroy(eventResult);
+
     NSCursor *cursor = nil;
     switch (wrtv_view_get_mouse_cursor(self->_webRenderView, (float)point.x, (float)point.y)) {
         case WRTV_MOUSE_CURSOR_T_POINTER:
@@ -526,6 +575,17 @@ static const wrtv_mouse_event_kind_t WRMouseEventKindFromNSEvent(NSEvent *event)
     }
     
     [cursor set];
+    return eventResultType;
+}
+
+- (uint8_t)mouseExited:(NSEvent *)event {
+    if (self->_webRenderView == NULL)
+        return WRTV_EVENT_RESULT_NONE;
+
+    wrtv_event_result_t *eventResult = wrtv_view_mouse_exited(self->_webRenderView);
+    uint8_t eventResultType = wrtv_event_result_get_type(eventResult);
+    wrtv_event_result_destroy(eventResult);
+    return eventResultType;
 }
 #else
 - (void)attachedToWindow {
@@ -600,7 +660,7 @@ static const wrtv_mouse_event_kind_t WRMouseEventKindFromNSEvent(NSEvent *event)
     if (![self isReady])
         return;
 
-    wrtv_view_select_all(self->_webRenderView);
+    wrtv_event_result_destroy(wrtv_view_select_all(self->_webRenderView));
 }
 
 - (BOOL)isReady {
@@ -266,7 +266,7 @@ static NSSpeechSynthesizer *gWRGlobalSpeechSynthesizer = nil;
     }
     
     NSTrackingAreaOptions trackingAreaOptions = NSTrackingActiveInKeyWindow |
-    NSTrackingInVisibleRect | NSTrackingMouseMoved;
+    NSTrackingInVisibleRect | NSTrackingMouseMoved | NSTrackingMouseEnteredAndExited;
     self->_trackingArea = [[NSTrackingArea alloc] initWithRect:[self bounds]
                                                        options:trackingAreaOptions
                                                          owner:self
@@ -309,10 +309,13 @@ static NSSpeechSynthesizer *gWRGlobalSpeechSynthesizer = nil;
 }
 
 - (void)mouseMoved:(NSEvent *)event {
-    [self->_textLayer mouseMoved:event];
+    if ([self->_textLayer mouseMoved:event] != WRTV_EVENT_RESULT_NONE)
+        [self setNeedsDisplay:YES];
 }
 
 - (void)mouseExited:(NSEvent *)event {
+    if ([self->_textLayer mouseExited:event] != WRTV_EVENT_RESULT_NONE)
+        [self setNeedsDisplay:YES];
     [[NSCursor arrowCursor] set];
 }
 
@@ -0,0 +1,72 @@
+// WRTextView/webrender-text-view/src/core_text_backend.rs
+//
+// Copyright © 2018 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use core_text::font::{self as ct_font, CTFont};
+use pilcrow::Font;
+use std::mem;
+use webrender_api::NativeFontHandle;
+use font_backend::{FontBackend, FontMetrics};
+
+pub struct CoreTextFontBackend;
+
+impl CoreTextFontBackend {
+    #[inline]
+    pub fn new() -> CoreTextFontBackend {
+        CoreTextFontBackend
+    }
+
+    fn ct_font(&self, native_font_handle: &NativeFontHandle, size: f32) -> CTFont {
+        // FIXME(pcwalton): Another workaround for version mismatch of `core-graphics`!
+        unsafe {
+            ct_font::new_from_CGFont(mem::transmute(&native_font_handle.0), size as f64)
+        }
+    }
+}
+
+impl FontBackend for CoreTextFontBackend {
+    fn native_font_handle(&self, font: &Font) -> NativeFontHandle {
+        // FIXME(pcwalton): Workaround for version mismatch of `core-graphics`!
+        unsafe {
+            NativeFontHandle(mem::transmute(font.native_font().copy_to_CGFont()))
+        }
+    }
+
+    fn font_metrics(&self, native_font_handle: &NativeFontHandle, size: f32) -> FontMetrics {
+        let ct_font = self.ct_font(native_font_handle, size);
+        let underline_thickness = ct_font.underline_thickness() as f32;
+        FontMetrics {
+            underline_position: ct_font.underline_position() as f32,
+            underline_thickness,
+            // Core Text doesn't expose the strikeout metrics, so do what browsers do and put
+            // the line through the middle of the lowercase letters.
+            strikeout_position: ct_font.x_height() as f32 * 0.5,
+            strikeout_thickness: underline_thickness,
+        }
+    }
+
+    fn glyph_index(&self, native_font_handle: &NativeFontHandle, character: char)
+                   -> Option<u32> {
+        // The size doesn't matter for the character-to-glyph mapping.
+        let ct_font = self.ct_font(native_font_handle, 12.0);
+        let mut characters = [0; 2];
+        let characters = character.encode_utf16(&mut characters);
+        let mut glyphs = [0; 2];
+        let found = unsafe {
+            ct_font.get_glyphs_for_characters(characters.as_ptr(),
+                                              glyphs.as_mut_ptr(),
+                                              characters.len() as isize)
+        };
+        if found && glyphs[0] != 0 {
+            Some(glyphs[0] as u32)
+        } else {
+            None
+        }
+    }
+}
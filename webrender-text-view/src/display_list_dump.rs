@@ -0,0 +1,99 @@
+// WRTextView/webrender-text-view/src/display_list_dump.rs
+//
+// Copyright © 2018 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use webrender_api::{BuiltDisplayList, ColorF, FontInstanceKey, ImageKey, LayoutRect};
+use webrender_api::{LineOrientation, LineStyle, SpecificDisplayItem};
+
+/// Serializes a display list as JSON, one item per line.
+///
+/// The output only depends on the display list contents, so it is suitable for snapshot tests.
+pub fn dump_display_list(display_list: &BuiltDisplayList) -> String {
+    let mut items = vec![];
+    let mut iter = display_list.iter();
+    while let Some(item) = iter.next() {
+        let fields = match *item.item() {
+            SpecificDisplayItem::Text(ref text) => {
+                let glyph_count = item.display_list().get(item.glyphs()).count();
+                format!("\"type\": \"text\", \"font_instance_key\": {}, \"color\": {}, \
+                         \"glyph_count\": {}",
+                        font_instance_key_to_json(&text.font_key),
+                        color_to_json(&text.color),
+                        glyph_count)
+            }
+            SpecificDisplayItem::Rectangle(ref rect) => {
+                format!("\"type\": \"rect\", \"color\": {}", color_to_json(&rect.color))
+            }
+            SpecificDisplayItem::Line(ref line) => {
+                // Only wavy lines have a separate stroke thickness; other lines fill their
+                // bounds across the orientation.
+                let thickness = match (line.style, line.orientation) {
+                    (LineStyle::Wavy, _) => line.wavy_line_thickness,
+                    (_, LineOrientation::Horizontal) => item.rect().size.height,
+                    (_, LineOrientation::Vertical) => item.rect().size.width,
+                };
+                format!("\"type\": \"line\", \"orientation\": \"{:?}\", \"style\": \"{:?}\", \
+                         \"thickness\": {:.2}, \"color\": {}",
+                        line.orientation,
+                        line.style,
+                        thickness,
+                        color_to_json(&line.color))
+            }
+            SpecificDisplayItem::Image(ref image) => {
+                format!("\"type\": \"image\", \"image_key\": {}, \"stretch_size\": [{:.2}, {:.2}]",
+                        image_key_to_json(&image.image_key),
+                        image.stretch_size.width,
+                        image.stretch_size.height)
+            }
+            SpecificDisplayItem::PushShadow(ref shadow) => {
+                format!("\"type\": \"push_shadow\", \"offset\": [{:.2}, {:.2}], \
+                         \"blur_radius\": {:.2}, \"color\": {}, \"clip\": {}",
+                        shadow.offset.x,
+                        shadow.offset.y,
+                        shadow.blur_radius,
+                        color_to_json(&shadow.color),
+                        rect_to_json(item.local_clip().clip_rect()))
+            }
+            SpecificDisplayItem::PopAllShadows => "\"type\": \"pop_all_shadows\"".to_owned(),
+            SpecificDisplayItem::PushStackingContext(_) => {
+                "\"type\": \"push_stacking_context\"".to_owned()
+            }
+            SpecificDisplayItem::PopStackingContext => {
+                "\"type\": \"pop_stacking_context\"".to_owned()
+            }
+            _ => "\"type\": \"other\"".to_owned(),
+        };
+        items.push(format!("  {{{}, \"bounds\": {}}}", fields, rect_to_json(&item.rect())));
+    }
+
+    if items.is_empty() {
+        return "[]\n".to_owned()
+    }
+    format!("[\n{}\n]\n", items.join(",\n"))
+}
+
+fn rect_to_json(rect: &LayoutRect) -> String {
+    format!("[{:.2}, {:.2}, {:.2}, {:.2}]",
+            rect.origin.x,
+            rect.origin.y,
+            rect.size.width,
+            rect.size.height)
+}
+
+fn color_to_json(color: &ColorF) -> String {
+    format!("[{:.3}, {:.3}, {:.3}, {:.3}]", color.r, color.g, color.b, color.a)
+}
+
+fn font_instance_key_to_json(key: &FontInstanceKey) -> String {
+    format!("[{}, {}]", (key.0).0, key.1)
+}
+
+fn image_key_to_json(key: &ImageKey) -> String {
+    format!("[{}, {}]", (key.0).0, key.1)
+}
@@ -8,20 +8,44 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
-use euclid::{Length, Size2D, TypedScale, TypedVector2D};
-use pilcrow::{Color, Document};
+use euclid::{Length, Size2D, TypedScale, TypedSideOffsets2D, TypedVector2D};
+use pilcrow::{Color, Document, TextLocation};
 use std::cmp;
 use std::ptr;
 use std::slice;
 use webrender_api::{DevicePoint, DeviceUintSize, LayoutPoint};
-use {Api, EventResult, GetProcAddressFn, ImageId, MouseCursor, MouseEventKind, View, ViewFlags};
+use {Api, EventResult, FindOptions, GetProcAddressFn, Highlight, HighlightId, HighlightStyle};
+use {ImageId, Key, LinkUnderline, Modifiers, MouseCursor, MouseEventKind, PointInfo, Theme};
+use {LONG_PRESS_DURATION, ThemeColor, TouchId, View, ViewFlags};
 
+pub type wrtv_find_options_t = u32;
+pub type wrtv_modifiers_t = u32;
 pub type wrtv_view_flags_t = u32;
 
 pub const WRTV_EVENT_RESULT_NONE: u8 = 0;
 pub const WRTV_EVENT_RESULT_OPEN_URL: u8 = 1;
+pub const WRTV_EVENT_RESULT_SELECTION_CHANGED: u8 = 2;
+pub const WRTV_EVENT_RESULT_LINK_HOVER_ENTER: u8 = 3;
+pub const WRTV_EVENT_RESULT_LINK_HOVER_LEAVE: u8 = 4;
+pub const WRTV_EVENT_RESULT_IMAGE_CLICKED: u8 = 5;
+pub const WRTV_EVENT_RESULT_CONTEXT_MENU: u8 = 6;
+pub const WRTV_EVENT_RESULT_DOCUMENT_EDITED: u8 = 7;
+pub const WRTV_EVENT_RESULT_TRANSFORM_CHANGED: u8 = 8;
+
+pub const WRTV_LONG_PRESS_DURATION: f64 = LONG_PRESS_DURATION;
+
+pub const WRTV_FIND_OPTIONS_CASE_SENSITIVE: u32 = 0x01;
+pub const WRTV_FIND_OPTIONS_WHOLE_WORD: u32 = 0x02;
+pub const WRTV_FIND_OPTIONS_REGEX: u32 = 0x04;
+
+pub const WRTV_MODIFIERS_SHIFT: u32 = 0x01;
+pub const WRTV_MODIFIERS_CONTROL: u32 = 0x02;
+pub const WRTV_MODIFIERS_ALT: u32 = 0x04;
+pub const WRTV_MODIFIERS_META: u32 = 0x08;
 
 pub const WRTV_VIEW_FLAGS_ENABLE_SUBPIXEL_AA: u32 = 1;
+pub const WRTV_VIEW_FLAGS_EDITABLE: u32 = 2;
+pub const WRTV_VIEW_FLAGS_SELECTION_HANDLES: u32 = 4;
 
 #[no_mangle]
 pub unsafe extern "C" fn wrtv_view_new(document: *mut Document,
@@ -46,6 +70,28 @@ pub unsafe extern "C" fn wrtv_view_new(document: *mut Document,
                                      ViewFlags::from_bits_truncate(flags))))
 }
 
+#[cfg(feature = "headless")]
+#[no_mangle]
+pub unsafe extern "C" fn wrtv_view_new_headless(document: *mut Document,
+                                                viewport_width: u32,
+                                                viewport_height: u32,
+                                                device_pixel_ratio: f32,
+                                                available_width: f32,
+                                                flags: wrtv_view_flags_t)
+                                                -> *mut View {
+    let document = Box::from_raw(document);
+    let viewport = DeviceUintSize::new(viewport_width, viewport_height);
+    let device_pixel_ratio = TypedScale::new(device_pixel_ratio);
+    let available_width = Length::new(available_width);
+    Box::into_raw(Box::new(View::new_headless(*document,
+                                              &viewport,
+                                              device_pixel_ratio,
+                                              available_width,
+                                              ViewFlags::from_bits_truncate(flags))))
+}
+
+/// Destroying a view frees its GL resources, so unless the view is headless the GL context it
+/// was created with must be current.
 #[no_mangle]
 pub unsafe extern "C" fn wrtv_view_destroy(view: *mut View) {
     drop(Box::from_raw(view))
@@ -65,34 +111,140 @@ pub unsafe extern "C" fn wrtv_view_get_layout_size(view: *mut View,
     *height = size.height;
 }
 
+#[no_mangle]
+pub unsafe extern "C" fn wrtv_view_get_translation(view: *mut View, x: *mut f32, y: *mut f32) {
+    let translation = (*view).translation();
+    *x = translation.x;
+    *y = translation.y;
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn wrtv_view_repaint(view: *mut View) {
     (*view).repaint()
 }
 
+#[no_mangle]
+pub unsafe extern "C" fn wrtv_view_render_to_buffer(view: *mut View,
+                                                    out_rgba: *mut u8,
+                                                    stride: usize)
+                                                    -> bool {
+    let height = (*view).viewport_size().height as usize;
+    let out_rgba = slice::from_raw_parts_mut(out_rgba, stride * height);
+    (*view).render_to_buffer(out_rgba, stride)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn wrtv_view_dump_display_list(view: *mut View) -> *mut String {
+    Box::into_raw(Box::new((*view).dump_display_list()))
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn wrtv_view_get_mouse_cursor(view: *mut View, x: f32, y: f32)
                                                     -> MouseCursor {
     (*view).get_mouse_cursor(&LayoutPoint::new(x, y))
 }
 
+/// The result must be freed with `wrtv_point_info_destroy()`.
+#[no_mangle]
+pub unsafe extern "C" fn wrtv_view_inspect_point(view: *mut View, x: f32, y: f32)
+                                                 -> *mut PointInfo {
+    Box::into_raw(Box::new((*view).inspect_point(&LayoutPoint::new(x, y))))
+}
+
+/// The result must be freed with `wrtv_event_result_destroy()`.
 #[no_mangle]
 pub unsafe extern "C" fn wrtv_view_mouse_down(view: *mut View,
                                               x: f32,
                                               y: f32,
-                                              kind: MouseEventKind) {
-    (*view).mouse_down(&LayoutPoint::new(x, y), kind)
+                                              kind: MouseEventKind,
+                                              modifiers: wrtv_modifiers_t)
+                                              -> *mut EventResult {
+    let modifiers = Modifiers::from_bits_truncate(modifiers);
+    Box::into_raw(Box::new((*view).mouse_down(&LayoutPoint::new(x, y), kind, modifiers)))
 }
 
+/// The result must be freed with `wrtv_event_result_destroy()`.
 #[no_mangle]
-pub unsafe extern "C" fn wrtv_view_mouse_up(view: *mut View, x: f32, y: f32, kind: MouseEventKind)
+pub unsafe extern "C" fn wrtv_view_mouse_up(view: *mut View,
+                                            x: f32,
+                                            y: f32,
+                                            kind: MouseEventKind,
+                                            modifiers: wrtv_modifiers_t)
                                             -> *mut EventResult {
-    Box::into_raw(Box::new((*view).mouse_up(&LayoutPoint::new(x, y), kind)))
+    let modifiers = Modifiers::from_bits_truncate(modifiers);
+    Box::into_raw(Box::new((*view).mouse_up(&LayoutPoint::new(x, y), kind, modifiers)))
+}
+
+/// The result must be freed with `wrtv_event_result_destroy()`.
+#[no_mangle]
+pub unsafe extern "C" fn wrtv_view_mouse_moved(view: *mut View, x: f32, y: f32)
+                                               -> *mut EventResult {
+    Box::into_raw(Box::new((*view).mouse_moved(&LayoutPoint::new(x, y))))
+}
+
+/// The result must be freed with `wrtv_event_result_destroy()`.
+#[no_mangle]
+pub unsafe extern "C" fn wrtv_view_mouse_exited(view: *mut View) -> *mut EventResult {
+    Box::into_raw(Box::new((*view).mouse_exited()))
+}
+
+/// The result must be freed with `wrtv_event_result_destroy()`.
+#[no_mangle]
+pub unsafe extern "C" fn wrtv_view_mouse_dragged(view: *mut View, x: f32, y: f32)
+                                                 -> *mut EventResult {
+    Box::into_raw(Box::new((*view).mouse_dragged(&LayoutPoint::new(x, y))))
+}
+
+/// The result must be freed with `wrtv_event_result_destroy()`.
+#[no_mangle]
+pub unsafe extern "C" fn wrtv_view_touch_began(view: *mut View,
+                                               touch_id: u64,
+                                               x: f32,
+                                               y: f32,
+                                               time: f64)
+                                               -> *mut EventResult {
+    let point = LayoutPoint::new(x, y);
+    Box::into_raw(Box::new((*view).touch_began(TouchId(touch_id), &point, time)))
+}
+
+/// The result must be freed with `wrtv_event_result_destroy()`.
+#[no_mangle]
+pub unsafe extern "C" fn wrtv_view_touch_moved(view: *mut View,
+                                               touch_id: u64,
+                                               x: f32,
+                                               y: f32,
+                                               time: f64)
+                                               -> *mut EventResult {
+    let point = LayoutPoint::new(x, y);
+    Box::into_raw(Box::new((*view).touch_moved(TouchId(touch_id), &point, time)))
+}
+
+/// The result must be freed with `wrtv_event_result_destroy()`.
+#[no_mangle]
+pub unsafe extern "C" fn wrtv_view_touch_ended(view: *mut View,
+                                               touch_id: u64,
+                                               x: f32,
+                                               y: f32,
+                                               time: f64)
+                                               -> *mut EventResult {
+    let point = LayoutPoint::new(x, y);
+    Box::into_raw(Box::new((*view).touch_ended(TouchId(touch_id), &point, time)))
+}
+
+/// The result must be freed with `wrtv_event_result_destroy()`.
+#[no_mangle]
+pub unsafe extern "C" fn wrtv_view_touch_cancelled(view: *mut View, touch_id: u64)
+                                                   -> *mut EventResult {
+    Box::into_raw(Box::new((*view).touch_cancelled(TouchId(touch_id))))
 }
 
+/// Call this `WRTV_LONG_PRESS_DURATION` seconds after a touch begins.
+///
+/// The result must be freed with `wrtv_event_result_destroy()`.
 #[no_mangle]
-pub unsafe extern "C" fn wrtv_view_mouse_dragged(view: *mut View, x: f32, y: f32) {
-    (*view).mouse_dragged(&LayoutPoint::new(x, y))
+pub unsafe extern "C" fn wrtv_view_touch_timer_fired(view: *mut View, time: f64)
+                                                     -> *mut EventResult {
+    Box::into_raw(Box::new((*view).touch_timer_fired(time)))
 }
 
 #[no_mangle]
@@ -113,6 +265,15 @@ pub unsafe extern "C" fn wrtv_view_set_available_width(view: *mut View, new_avai
     (*view).set_available_width(Length::new(new_available_width))
 }
 
+#[no_mangle]
+pub unsafe extern "C" fn wrtv_view_set_content_insets(view: *mut View,
+                                                      top: f32,
+                                                      right: f32,
+                                                      bottom: f32,
+                                                      left: f32) {
+    (*view).set_content_insets(&TypedSideOffsets2D::new(top, right, bottom, left))
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn wrtv_view_set_translation(view: *mut View, x: f32, y: f32) {
     (*view).set_translation(&TypedVector2D::new(x, y))
@@ -128,6 +289,71 @@ pub unsafe extern "C" fn wrtv_view_set_scale(view: *mut View, scale: f32) {
     (*view).set_scale(scale)
 }
 
+#[no_mangle]
+pub unsafe extern "C" fn wrtv_view_get_scale(view: *mut View) -> f32 {
+    (*view).scale()
+}
+
+/// Returns a copy of the view's theme, to be modified and passed to `wrtv_view_set_theme()`.
+#[no_mangle]
+pub unsafe extern "C" fn wrtv_view_copy_theme(view: *mut View) -> *mut Theme {
+    Box::into_raw(Box::new((*view).theme().clone()))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn wrtv_view_set_theme(view: *mut View, theme: *const Theme) {
+    (*view).set_theme((*theme).clone())
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn wrtv_theme_new() -> *mut Theme {
+    Box::into_raw(Box::new(Theme::default()))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn wrtv_theme_destroy(theme: *mut Theme) {
+    drop(Box::from_raw(theme))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn wrtv_theme_set_color(theme: *mut Theme,
+                                              which: ThemeColor,
+                                              r: u8,
+                                              g: u8,
+                                              b: u8,
+                                              a: u8) {
+    (*theme).set_color(which, Color::new(r, g, b, a))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn wrtv_view_set_page_background_color(view: *mut View,
+                                                             r: u8,
+                                                             g: u8,
+                                                             b: u8,
+                                                             a: u8) {
+    (*view).set_page_background_color(Color::new(r, g, b, a))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn wrtv_theme_set_link_underline(theme: *mut Theme,
+                                                       link_underline: LinkUnderline) {
+    (*theme).link_underline = link_underline
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn wrtv_view_set_focused(view: *mut View, focused: bool) {
+    (*view).set_focused(focused)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn wrtv_view_set_inactive_selection_background_color(view: *mut View,
+                                                                            r: u8,
+                                                                            g: u8,
+                                                                            b: u8,
+                                                                            a: u8) {
+    (*view).set_inactive_selection_background_color(Color::new(r, g, b, a))
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn wrtv_view_set_selection_background_color(view: *mut View,
                                                                   r: u8,
@@ -138,8 +364,38 @@ pub unsafe extern "C" fn wrtv_view_set_selection_background_color(view: *mut Vie
 }
 
 #[no_mangle]
-pub unsafe extern "C" fn wrtv_view_select_all(view: *mut View) {
-    (*view).select_all()
+pub unsafe extern "C" fn wrtv_view_set_caret_color(view: *mut View, r: u8, g: u8, b: u8, a: u8) {
+    (*view).set_caret_color(Color::new(r, g, b, a))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn wrtv_view_set_caret_width(view: *mut View, width: f32) {
+    (*view).set_caret_width(width)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn wrtv_view_has_caret(view: *mut View) -> bool {
+    (*view).has_caret()
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn wrtv_view_set_caret_blink_phase(view: *mut View, visible: bool) {
+    (*view).set_caret_blink_phase(visible)
+}
+
+/// The result must be freed with `wrtv_event_result_destroy()`.
+#[no_mangle]
+pub unsafe extern "C" fn wrtv_view_key_down(view: *mut View,
+                                            key: Key,
+                                            modifiers: wrtv_modifiers_t)
+                                            -> *mut EventResult {
+    Box::into_raw(Box::new((*view).key_down(key, Modifiers::from_bits_truncate(modifiers))))
+}
+
+/// The result must be freed with `wrtv_event_result_destroy()`.
+#[no_mangle]
+pub unsafe extern "C" fn wrtv_view_select_all(view: *mut View) -> *mut EventResult {
+    Box::into_raw(Box::new((*view).select_all()))
 }
 
 #[no_mangle]
@@ -152,6 +408,225 @@ pub unsafe extern "C" fn wrtv_view_document_changed(view: *mut View) {
     (*view).document_changed()
 }
 
+/// The result must be freed with `wrtv_event_result_destroy()`.
+#[no_mangle]
+pub unsafe extern "C" fn wrtv_view_insert_text(view: *mut View,
+                                               paragraph_index: usize,
+                                               character_index: usize,
+                                               text: *const u8,
+                                               text_len: usize)
+                                               -> *mut EventResult {
+    let text = String::from_utf8_lossy(slice::from_raw_parts(text, text_len));
+    let location = TextLocation::new(paragraph_index, character_index);
+    Box::into_raw(Box::new((*view).insert_text(&location, &text)))
+}
+
+/// The result must be freed with `wrtv_event_result_destroy()`.
+#[no_mangle]
+pub unsafe extern "C" fn wrtv_view_delete_range(view: *mut View,
+                                                start_paragraph_index: usize,
+                                                start_character_index: usize,
+                                                end_paragraph_index: usize,
+                                                end_character_index: usize)
+                                                -> *mut EventResult {
+    let start = TextLocation::new(start_paragraph_index, start_character_index);
+    let end = TextLocation::new(end_paragraph_index, end_character_index);
+    Box::into_raw(Box::new((*view).delete_range(start..end)))
+}
+
+/// The result must be freed with `wrtv_event_result_destroy()`.
+#[no_mangle]
+pub unsafe extern "C" fn wrtv_view_delete_backward(view: *mut View) -> *mut EventResult {
+    Box::into_raw(Box::new((*view).delete_backward()))
+}
+
+/// The result must be freed with `wrtv_event_result_destroy()`.
+#[no_mangle]
+pub unsafe extern "C" fn wrtv_view_delete_forward(view: *mut View) -> *mut EventResult {
+    Box::into_raw(Box::new((*view).delete_forward()))
+}
+
+/// The result must be freed with `wrtv_event_result_destroy()`.
+#[no_mangle]
+pub unsafe extern "C" fn wrtv_view_replace_selection(view: *mut View,
+                                                     text: *const u8,
+                                                     text_len: usize)
+                                                     -> *mut EventResult {
+    let text = String::from_utf8_lossy(slice::from_raw_parts(text, text_len));
+    Box::into_raw(Box::new((*view).replace_selection(&text)))
+}
+
+/// The result must be freed with `wrtv_event_result_destroy()`.
+#[no_mangle]
+pub unsafe extern "C" fn wrtv_view_undo(view: *mut View) -> *mut EventResult {
+    Box::into_raw(Box::new((*view).undo()))
+}
+
+/// The result must be freed with `wrtv_event_result_destroy()`.
+#[no_mangle]
+pub unsafe extern "C" fn wrtv_view_redo(view: *mut View) -> *mut EventResult {
+    Box::into_raw(Box::new((*view).redo()))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn wrtv_view_can_undo(view: *mut View) -> bool {
+    (*view).can_undo()
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn wrtv_view_can_redo(view: *mut View) -> bool {
+    (*view).can_redo()
+}
+
+/// The result must be freed with `wrtv_event_result_destroy()`.
+#[no_mangle]
+pub unsafe extern "C" fn wrtv_view_set_marked_text(view: *mut View,
+                                                   text: *const u8,
+                                                   text_len: usize,
+                                                   selected_start: usize,
+                                                   selected_len: usize)
+                                                   -> *mut EventResult {
+    let text = String::from_utf8_lossy(slice::from_raw_parts(text, text_len));
+    let selected_range = selected_start..(selected_start + selected_len);
+    Box::into_raw(Box::new((*view).set_marked_text(&text, selected_range)))
+}
+
+/// The result must be freed with `wrtv_event_result_destroy()`.
+#[no_mangle]
+pub unsafe extern "C" fn wrtv_view_unmark_text(view: *mut View) -> *mut EventResult {
+    Box::into_raw(Box::new((*view).unmark_text()))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn wrtv_view_has_marked_text(view: *mut View) -> bool {
+    (*view).has_marked_text()
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn wrtv_view_get_caret_rect(view: *mut View,
+                                                  x: *mut f32,
+                                                  y: *mut f32,
+                                                  width: *mut f32,
+                                                  height: *mut f32)
+                                                  -> bool {
+    match (*view).caret_rect() {
+        None => false,
+        Some(rect) => {
+            *x = rect.origin.x;
+            *y = rect.origin.y;
+            *width = rect.size.width;
+            *height = rect.size.height;
+            true
+        }
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn wrtv_view_find(view: *mut View,
+                                        query: *const u8,
+                                        query_len: usize,
+                                        options: wrtv_find_options_t)
+                                        -> usize {
+    let query = String::from_utf8_lossy(slice::from_raw_parts(query, query_len));
+    (*view).find(&query, FindOptions::from_bits_truncate(options)).len()
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn wrtv_view_find_next(view: *mut View) -> bool {
+    (*view).find_next().is_some()
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn wrtv_view_find_previous(view: *mut View) -> bool {
+    (*view).find_previous().is_some()
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn wrtv_view_clear_find(view: *mut View) {
+    (*view).clear_find()
+}
+
+/// Returns the index of the current match, or -1 if there isn't one.
+#[no_mangle]
+pub unsafe extern "C" fn wrtv_view_get_current_find_match(view: *mut View) -> isize {
+    match (*view).current_find_match() {
+        None => -1,
+        Some(index) => index as isize,
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn wrtv_view_get_find_match(view: *mut View,
+                                                  index: usize,
+                                                  start_paragraph_index: *mut usize,
+                                                  start_character_index: *mut usize,
+                                                  end_paragraph_index: *mut usize,
+                                                  end_character_index: *mut usize)
+                                                  -> bool {
+    match (*view).find_matches().get(index) {
+        None => false,
+        Some(find_match) => {
+            *start_paragraph_index = find_match.start.paragraph_index;
+            *start_character_index = find_match.start.character_index;
+            *end_paragraph_index = find_match.end.paragraph_index;
+            *end_character_index = find_match.end.character_index;
+            true
+        }
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn wrtv_view_set_find_match_color(view: *mut View,
+                                                        r: u8,
+                                                        g: u8,
+                                                        b: u8,
+                                                        a: u8) {
+    (*view).set_find_match_color(Color::new(r, g, b, a))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn wrtv_view_set_current_find_match_color(view: *mut View,
+                                                                r: u8,
+                                                                g: u8,
+                                                                b: u8,
+                                                                a: u8) {
+    (*view).set_current_find_match_color(Color::new(r, g, b, a))
+}
+
+/// Returns the ID of the new highlight.
+#[no_mangle]
+pub unsafe extern "C" fn wrtv_view_add_highlight(view: *mut View,
+                                                 start_paragraph_index: usize,
+                                                 start_character_index: usize,
+                                                 end_paragraph_index: usize,
+                                                 end_character_index: usize,
+                                                 r: u8,
+                                                 g: u8,
+                                                 b: u8,
+                                                 a: u8,
+                                                 style: HighlightStyle,
+                                                 z_index: i32)
+                                                 -> u32 {
+    let start = TextLocation::new(start_paragraph_index, start_character_index);
+    let end = TextLocation::new(end_paragraph_index, end_character_index);
+    (*view).add_highlight(Highlight {
+        range: start..end,
+        color: Color::new(r, g, b, a),
+        style,
+        z_index,
+    }).0
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn wrtv_view_remove_highlight(view: *mut View, highlight_id: u32) -> bool {
+    (*view).remove_highlight(HighlightId(highlight_id)).is_some()
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn wrtv_view_clear_highlights(view: *mut View) {
+    (*view).clear_highlights()
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn wrtv_view_set_image_size(view: *mut View,
                                                   image_id: u32,
@@ -178,27 +653,122 @@ pub unsafe extern "C" fn wrtv_event_result_destroy(event_result: *mut EventResul
 pub unsafe extern "C" fn wrtv_event_result_get_type(event_result: *const EventResult) -> u8 {
     match *event_result {
         EventResult::None => WRTV_EVENT_RESULT_NONE,
-        EventResult::OpenUrl(_) => WRTV_EVENT_RESULT_OPEN_URL,
+        EventResult::OpenUrl(..) => WRTV_EVENT_RESULT_OPEN_URL,
+        EventResult::SelectionChanged => WRTV_EVENT_RESULT_SELECTION_CHANGED,
+        EventResult::LinkHoverEnter(_) => WRTV_EVENT_RESULT_LINK_HOVER_ENTER,
+        EventResult::LinkHoverLeave(_) => WRTV_EVENT_RESULT_LINK_HOVER_LEAVE,
+        EventResult::ImageClicked(_) => WRTV_EVENT_RESULT_IMAGE_CLICKED,
+        EventResult::ContextMenu(_) => WRTV_EVENT_RESULT_CONTEXT_MENU,
+        EventResult::DocumentEdited => WRTV_EVENT_RESULT_DOCUMENT_EDITED,
+        EventResult::TransformChanged => WRTV_EVENT_RESULT_TRANSFORM_CHANGED,
     }
 }
 
+/// Returns the length of the event's URL, or 0 if it has none.
 #[no_mangle]
 pub unsafe extern "C" fn wrtv_event_result_get_string_len(event_result: *const EventResult)
                                                           -> usize {
-    match *event_result {
-        EventResult::None => 0,
-        EventResult::OpenUrl(ref url) => url.len(),
-    }
+    (*event_result).url().map_or(0, |url| url.len())
 }
 
 #[no_mangle]
 pub unsafe extern "C" fn wrtv_event_result_get_string(event_result: *const EventResult,
                                                       buffer: *mut u8,
                                                       buffer_len: usize) {
+    if let Some(url) = (*event_result).url() {
+        ptr::copy_nonoverlapping(url.as_ptr(), buffer, cmp::min(url.len(), buffer_len))
+    }
+}
+
+/// Returns the modifiers held when a link was activated, or 0 for other events.
+#[no_mangle]
+pub unsafe extern "C" fn wrtv_event_result_get_modifiers(event_result: *const EventResult)
+                                                         -> wrtv_modifiers_t {
     match *event_result {
-        EventResult::None => {}
-        EventResult::OpenUrl(ref url) => {
-            ptr::copy_nonoverlapping(url.as_ptr(), buffer, cmp::min(url.len(), buffer_len))
+        EventResult::OpenUrl(_, modifiers) => modifiers.bits(),
+        _ => 0,
+    }
+}
+
+/// Returns false if the event doesn't concern an image.
+#[no_mangle]
+pub unsafe extern "C" fn wrtv_event_result_get_image_id(event_result: *const EventResult,
+                                                        image_id: *mut u32)
+                                                        -> bool {
+    match (*event_result).image_id() {
+        None => false,
+        Some(ImageId(id)) => {
+            *image_id = id;
+            true
         }
     }
 }
+
+/// Returns a copy of the context menu event's point info, or null for other events.
+#[no_mangle]
+pub unsafe extern "C" fn wrtv_event_result_copy_point_info(event_result: *const EventResult)
+                                                           -> *mut PointInfo {
+    match *event_result {
+        EventResult::ContextMenu(ref point_info) => Box::into_raw(Box::new(point_info.clone())),
+        _ => ptr::null_mut(),
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn wrtv_point_info_destroy(point_info: *mut PointInfo) {
+    drop(Box::from_raw(point_info))
+}
+
+/// Returns the length of the link URL, or 0 if there's no link.
+#[no_mangle]
+pub unsafe extern "C" fn wrtv_point_info_get_url_len(point_info: *const PointInfo) -> usize {
+    (*point_info).url.as_ref().map_or(0, |url| url.len())
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn wrtv_point_info_get_url(point_info: *const PointInfo,
+                                                 buffer: *mut u8,
+                                                 buffer_len: usize) {
+    if let Some(ref url) = (*point_info).url {
+        ptr::copy_nonoverlapping(url.as_ptr(), buffer, cmp::min(url.len(), buffer_len))
+    }
+}
+
+/// Returns false if there's no image.
+#[no_mangle]
+pub unsafe extern "C" fn wrtv_point_info_get_image_id(point_info: *const PointInfo,
+                                                      image_id: *mut u32)
+                                                      -> bool {
+    match (*point_info).image_id {
+        None => false,
+        Some(ImageId(id)) => {
+            *image_id = id;
+            true
+        }
+    }
+}
+
+/// Returns false if the point isn't over text.
+#[no_mangle]
+pub unsafe extern "C" fn wrtv_point_info_get_word_range(point_info: *const PointInfo,
+                                                        start_paragraph_index: *mut usize,
+                                                        start_character_index: *mut usize,
+                                                        end_paragraph_index: *mut usize,
+                                                        end_character_index: *mut usize)
+                                                        -> bool {
+    match (*point_info).word_range {
+        None => false,
+        Some(ref word_range) => {
+            *start_paragraph_index = word_range.start.paragraph_index;
+            *start_character_index = word_range.start.character_index;
+            *end_paragraph_index = word_range.end.paragraph_index;
+            *end_character_index = word_range.end.character_index;
+            true
+        }
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn wrtv_point_info_is_in_selection(point_info: *const PointInfo) -> bool {
+    (*point_info).in_selection
+}
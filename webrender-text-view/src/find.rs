@@ -0,0 +1,183 @@
+// WRTextView/webrender-text-view/src/find.rs
+//
+// Copyright © 2018 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use pilcrow::{Color, Document, TextLocation};
+use regex::{self, Regex, RegexBuilder};
+use std::ops::Range;
+use {CaretAffinity, SectionExt, View};
+
+bitflags! {
+    pub struct FindOptions: u32 {
+        const CASE_SENSITIVE = 0x01;
+        const WHOLE_WORD = 0x02;
+        /// Treat the query as a regular expression rather than literal text.
+        const REGEX = 0x04;
+    }
+}
+
+impl View {
+    /// Highlights every match of `query` in the document and returns them.
+    ///
+    /// The first match at or after the selection becomes the current match and is scrolled into
+    /// view. Matches are kept up to date as the document is edited, until `clear_find` is called.
+    pub fn find(&mut self, query: &str, options: FindOptions) -> &[Range<TextLocation>] {
+        self.find_query = Some((query.to_owned(), options));
+        self.find_matches = find_in_document(&self.document, query, options);
+
+        let start = match self.selection {
+            None => TextLocation::beginning(),
+            Some(ref selection) => selection.range.start,
+        };
+        self.current_find_match = if self.find_matches.is_empty() {
+            None
+        } else {
+            Some(self.find_matches.iter().position(|find_match| find_match.start >= start)
+                                         .unwrap_or(0))
+        };
+
+        self.scroll_current_find_match_to_visible();
+        self.rebuild_display_list();
+        &self.find_matches
+    }
+
+    /// Makes the next match current, wrapping around at the end, and scrolls it into view.
+    pub fn find_next(&mut self) -> Option<Range<TextLocation>> {
+        self.move_current_find_match(true)
+    }
+
+    /// Makes the previous match current, wrapping around at the start, and scrolls it into view.
+    pub fn find_previous(&mut self) -> Option<Range<TextLocation>> {
+        self.move_current_find_match(false)
+    }
+
+    pub fn clear_find(&mut self) {
+        self.find_query = None;
+        self.find_matches.clear();
+        self.current_find_match = None;
+        self.rebuild_display_list();
+    }
+
+    #[inline]
+    pub fn find_matches(&self) -> &[Range<TextLocation>] {
+        &self.find_matches
+    }
+
+    #[inline]
+    pub fn current_find_match(&self) -> Option<usize> {
+        self.current_find_match
+    }
+
+    pub fn set_find_match_color(&mut self, color: Color) {
+        self.theme.find_match_color = color;
+        self.rebuild_display_list();
+    }
+
+    pub fn set_current_find_match_color(&mut self, color: Color) {
+        self.theme.current_find_match_color = color;
+        self.rebuild_display_list();
+    }
+
+    /// Reruns the current search. Call this when the document changes.
+    pub(crate) fn refresh_find_matches(&mut self) {
+        let (query, options) = match self.find_query {
+            None => return,
+            Some((ref query, options)) => (query.clone(), options),
+        };
+        self.find_matches = find_in_document(&self.document, &query, options);
+        if self.current_find_match.map_or(false, |index| index >= self.find_matches.len()) {
+            self.current_find_match = None
+        }
+    }
+
+    fn move_current_find_match(&mut self, forward: bool) -> Option<Range<TextLocation>> {
+        let match_count = self.find_matches.len();
+        if match_count == 0 {
+            return None
+        }
+
+        let index = match (self.current_find_match, forward) {
+            (None, true) => 0,
+            (None, false) => match_count - 1,
+            (Some(index), true) => (index + 1) % match_count,
+            (Some(index), false) => (index + match_count - 1) % match_count,
+        };
+        self.current_find_match = Some(index);
+
+        self.scroll_current_find_match_to_visible();
+        self.rebuild_display_list();
+        Some(self.find_matches[index].clone())
+    }
+
+    fn scroll_current_find_match_to_visible(&mut self) {
+        let find_match = match self.current_find_match {
+            None => return,
+            Some(index) => self.find_matches[index].clone(),
+        };
+        let start_bounds =
+            self.section.caret_bounds(&find_match.start, CaretAffinity::Downstream, 0.0);
+        let end_bounds = self.section.caret_bounds(&find_match.end, CaretAffinity::Downstream, 0.0);
+        if let (Some(start_bounds), Some(end_bounds)) = (start_bounds, end_bounds) {
+            self.scroll_rect_to_visible(&start_bounds.union(&end_bounds))
+        }
+    }
+}
+
+/// Returns every match of `query` in the document, in document order.
+///
+/// Matches never span paragraphs. An invalid regular expression matches nothing.
+fn find_in_document(document: &Document, query: &str, options: FindOptions)
+                    -> Vec<Range<TextLocation>> {
+    let regex = match build_regex(query, options) {
+        None => return vec![],
+        Some(regex) => regex,
+    };
+
+    let mut matches = vec![];
+    for (paragraph_index, paragraph) in document.paragraphs().iter().enumerate() {
+        let paragraph_range = TextLocation::new(paragraph_index, 0)..
+            TextLocation::new(paragraph_index, paragraph.char_len());
+        let text = document.copy_string_in_range(paragraph_range);
+
+        // Regex matches are in bytes, but text locations are in characters.
+        let mut char_index = 0;
+        let mut byte_index = 0;
+        for regex_match in regex.find_iter(&text) {
+            if regex_match.start() == regex_match.end() {
+                continue
+            }
+            char_index += text[byte_index..regex_match.start()].chars().count();
+            let start = TextLocation::new(paragraph_index, char_index);
+            char_index += regex_match.as_str().chars().count();
+            let end = TextLocation::new(paragraph_index, char_index);
+            byte_index = regex_match.end();
+            matches.push(start..end);
+        }
+    }
+    matches
+}
+
+fn build_regex(query: &str, options: FindOptions) -> Option<Regex> {
+    if query.is_empty() {
+        return None
+    }
+
+    let mut pattern = if options.contains(FindOptions::REGEX) {
+        query.to_owned()
+    } else {
+        regex::escape(query)
+    };
+    if options.contains(FindOptions::WHOLE_WORD) {
+        pattern = format!(r"\b(?:{})\b", pattern);
+    }
+
+    RegexBuilder::new(&pattern).case_insensitive(!options.contains(FindOptions::CASE_SENSITIVE))
+                               .build()
+                               .ok()
+}
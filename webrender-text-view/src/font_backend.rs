@@ -0,0 +1,39 @@
+// WRTextView/webrender-text-view/src/font_backend.rs
+//
+// Copyright © 2018 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use pilcrow::Font;
+use webrender_api::NativeFontHandle;
+
+#[cfg(any(target_os = "macos", target_os = "ios"))]
+pub use core_text_backend::CoreTextFontBackend as DefaultFontBackend;
+#[cfg(not(any(target_os = "macos", target_os = "ios")))]
+pub use freetype_backend::FreeTypeFontBackend as DefaultFontBackend;
+
+/// The platform font services that the view needs in order to hand fonts to WebRender.
+pub trait FontBackend {
+    /// Creates the handle that WebRender uses to rasterize glyphs from `font`.
+    fn native_font_handle(&self, font: &Font) -> NativeFontHandle;
+
+    /// Returns the decoration metrics of the font at the given size, in layout pixels.
+    fn font_metrics(&self, native_font_handle: &NativeFontHandle, size: f32) -> FontMetrics;
+
+    /// Returns the index of the glyph that `character` maps to, if the font has one.
+    fn glyph_index(&self, native_font_handle: &NativeFontHandle, character: char)
+                   -> Option<u32>;
+}
+
+/// Positions are measured upward from the baseline, so underlines are typically negative.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FontMetrics {
+    pub underline_position: f32,
+    pub underline_thickness: f32,
+    pub strikeout_position: f32,
+    pub strikeout_thickness: f32,
+}
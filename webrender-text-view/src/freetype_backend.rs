@@ -0,0 +1,199 @@
+// WRTextView/webrender-text-view/src/freetype_backend.rs
+//
+// Copyright © 2018 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use fontconfig::fontconfig::{FcChar8, FcConfigSubstitute, FcDefaultSubstitute, FcFontMatch};
+use fontconfig::fontconfig::{FcMatchPattern, FcPatternAddString, FcPatternCreate};
+use fontconfig::fontconfig::{FcPatternDestroy, FcPatternGetInteger, FcPatternGetString};
+use fontconfig::fontconfig::{FcResultMatch};
+use freetype::freetype::{FT_Done_Face, FT_Done_FreeType, FT_Face, FT_Get_Char_Index};
+use freetype::freetype::{FT_Get_Sfnt_Table, FT_Init_FreeType, FT_Library, FT_New_Face};
+use freetype::freetype::{FT_Sfnt_Tag, FT_ULong};
+use freetype::tt_os2::TT_OS2;
+use libc::{c_char, c_int};
+use pilcrow::Font;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::collections::hash_map::Entry;
+use std::ffi::{CStr, CString};
+use std::ptr;
+use webrender_api::NativeFontHandle;
+use font_backend::{FontBackend, FontMetrics};
+
+const FC_FILE: &'static [u8] = b"file\0";
+const FC_INDEX: &'static [u8] = b"index\0";
+const FC_POSTSCRIPT_NAME: &'static [u8] = b"postscriptname\0";
+
+/// Finds fonts with fontconfig and reads their metrics with FreeType.
+///
+/// WebRender opens the font files itself, so the native handles are just paths.
+pub struct FreeTypeFontBackend {
+    library: FT_Library,
+    faces: RefCell<HashMap<(String, u32), FT_Face>>,
+}
+
+impl FreeTypeFontBackend {
+    pub fn new() -> FreeTypeFontBackend {
+        let mut library = ptr::null_mut();
+        unsafe {
+            let result = FT_Init_FreeType(&mut library);
+            assert!(result == 0, "Failed to initialize FreeType!");
+        }
+        FreeTypeFontBackend {
+            library,
+            faces: RefCell::new(HashMap::new()),
+        }
+    }
+
+    fn face(&self, native_font_handle: &NativeFontHandle) -> Option<FT_Face> {
+        let key = (native_font_handle.pathname.clone(), native_font_handle.index);
+        match self.faces.borrow_mut().entry(key) {
+            Entry::Occupied(entry) => Some(*entry.get()),
+            Entry::Vacant(entry) => {
+                let pathname = match CString::new(native_font_handle.pathname.clone()) {
+                    Err(_) => return None,
+                    Ok(pathname) => pathname,
+                };
+                let mut face = ptr::null_mut();
+                let result = unsafe {
+                    FT_New_Face(self.library,
+                                pathname.as_ptr(),
+                                native_font_handle.index as _,
+                                &mut face)
+                };
+                if result != 0 || face.is_null() {
+                    return None
+                }
+                Some(*entry.insert(face))
+            }
+        }
+    }
+}
+
+impl Drop for FreeTypeFontBackend {
+    fn drop(&mut self) {
+        unsafe {
+            for (_, face) in self.faces.borrow_mut().drain() {
+                FT_Done_Face(face);
+            }
+            FT_Done_FreeType(self.library);
+        }
+    }
+}
+
+impl FontBackend for FreeTypeFontBackend {
+    fn native_font_handle(&self, font: &Font) -> NativeFontHandle {
+        // Fall back to fontconfig's default face if the font can't be found, and to an empty
+        // handle, which WebRender draws nothing with, if there are no fonts at all.
+        let postscript_name = CString::new(font.postscript_name()).ok();
+        let native_font_handle = match postscript_name.and_then(|name| match_font(Some(&name))) {
+            Some(native_font_handle) => Some(native_font_handle),
+            None => match_font(None),
+        };
+        native_font_handle.unwrap_or_else(|| {
+            NativeFontHandle {
+                pathname: String::new(),
+                index: 0,
+            }
+        })
+    }
+
+    fn font_metrics(&self, native_font_handle: &NativeFontHandle, size: f32) -> FontMetrics {
+        let face = match self.face(native_font_handle) {
+            None => {
+                return FontMetrics {
+                    underline_position: -size * 0.1,
+                    underline_thickness: size * 0.05,
+                    strikeout_position: size * 0.25,
+                    strikeout_thickness: size * 0.05,
+                }
+            }
+            Some(face) => face,
+        };
+
+        unsafe {
+            let scale = size / (*face).units_per_EM as f32;
+            let underline_position = (*face).underline_position as f32 * scale;
+            let underline_thickness = (*face).underline_thickness as f32 * scale;
+
+            // Fall back to a third of the ascent, which is roughly half the x-height, if the font
+            // has no OS/2 table.
+            let os2 = FT_Get_Sfnt_Table(face, FT_Sfnt_Tag::FT_SFNT_OS2) as *const TT_OS2;
+            let (strikeout_position, strikeout_thickness) = if os2.is_null() {
+                ((*face).ascender as f32 * scale / 3.0, underline_thickness)
+            } else {
+                ((*os2).yStrikeoutPosition as f32 * scale, (*os2).yStrikeoutSize as f32 * scale)
+            };
+
+            FontMetrics {
+                underline_position,
+                underline_thickness,
+                strikeout_position,
+                strikeout_thickness,
+            }
+        }
+    }
+
+    fn glyph_index(&self, native_font_handle: &NativeFontHandle, character: char)
+                   -> Option<u32> {
+        let face = match self.face(native_font_handle) {
+            None => return None,
+            Some(face) => face,
+        };
+        match unsafe { FT_Get_Char_Index(face, character as FT_ULong) } {
+            0 => None,
+            glyph_index => Some(glyph_index as u32),
+        }
+    }
+}
+
+// Asks fontconfig for the font with the given PostScript name, or for its default font if `None`.
+fn match_font(postscript_name: Option<&CStr>) -> Option<NativeFontHandle> {
+    unsafe {
+        let pattern = FcPatternCreate();
+        if let Some(postscript_name) = postscript_name {
+            FcPatternAddString(pattern,
+                               FC_POSTSCRIPT_NAME.as_ptr() as *const c_char,
+                               postscript_name.as_ptr() as *const FcChar8);
+        }
+        FcConfigSubstitute(ptr::null_mut(), pattern, FcMatchPattern);
+        FcDefaultSubstitute(pattern);
+
+        let mut result = FcResultMatch;
+        let matched = FcFontMatch(ptr::null_mut(), pattern, &mut result);
+        FcPatternDestroy(pattern);
+        if matched.is_null() {
+            return None
+        }
+
+        let mut pathname: *mut FcChar8 = ptr::null_mut();
+        let mut index: c_int = 0;
+        if FcPatternGetString(matched,
+                              FC_FILE.as_ptr() as *const c_char,
+                              0,
+                              &mut pathname) != FcResultMatch {
+            FcPatternDestroy(matched);
+            return None
+        }
+        if FcPatternGetInteger(matched,
+                               FC_INDEX.as_ptr() as *const c_char,
+                               0,
+                               &mut index) != FcResultMatch {
+            index = 0
+        }
+
+        let pathname = CStr::from_ptr(pathname as *const c_char).to_string_lossy().into_owned();
+        FcPatternDestroy(matched);
+
+        Some(NativeFontHandle {
+            pathname,
+            index: index as u32,
+        })
+    }
+}
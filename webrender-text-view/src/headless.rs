@@ -0,0 +1,66 @@
+// WRTextView/webrender-text-view/src/headless.rs
+//
+// Copyright © 2018 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use gleam::gl;
+use libc::c_char;
+use osmesa_sys::{OSMesaContext, OSMesaCreateContextExt, OSMesaDestroyContext};
+use osmesa_sys::{OSMesaGetProcAddress, OSMesaMakeCurrent};
+use std::mem;
+use std::os::raw::c_void;
+use std::ptr;
+use webrender_api::DeviceUintSize;
+
+/// An OSMesa software rendering context, for machines without a GPU or a window system.
+pub struct HeadlessContext {
+    context: OSMesaContext,
+    buffer: Vec<u8>,
+    size: DeviceUintSize,
+}
+
+impl HeadlessContext {
+    pub fn new() -> HeadlessContext {
+        let context = unsafe { OSMesaCreateContextExt(gl::RGBA, 24, 8, 0, ptr::null_mut()) };
+        assert!(!context.is_null(), "Failed to create the OSMesa context!");
+        HeadlessContext {
+            context,
+            buffer: vec![],
+            size: DeviceUintSize::zero(),
+        }
+    }
+
+    /// Makes this context current, reallocating the color buffer if the size changed.
+    pub fn make_current(&mut self, size: &DeviceUintSize) {
+        if self.size != *size {
+            self.buffer = vec![0; size.width as usize * size.height as usize * 4];
+            self.size = *size;
+        }
+
+        unsafe {
+            let succeeded = OSMesaMakeCurrent(self.context,
+                                              self.buffer.as_mut_ptr() as *mut c_void,
+                                              gl::UNSIGNED_BYTE,
+                                              size.width as i32,
+                                              size.height as i32);
+            assert!(succeeded != 0, "Failed to make the OSMesa context current!");
+        }
+    }
+}
+
+impl Drop for HeadlessContext {
+    fn drop(&mut self) {
+        unsafe {
+            OSMesaDestroyContext(self.context)
+        }
+    }
+}
+
+pub unsafe extern "C" fn get_proc_address(symbol: *const c_char) -> *const c_void {
+    mem::transmute(OSMesaGetProcAddress(symbol))
+}
@@ -0,0 +1,93 @@
+// WRTextView/webrender-text-view/src/highlight.rs
+//
+// Copyright © 2018 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use pilcrow::{Color, TextLocation};
+use std::cmp;
+use std::ops::Range;
+use undo::Edit;
+use View;
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct HighlightId(pub u32);
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[repr(C)]
+pub enum HighlightStyle {
+    Background = 0,
+    Underline,
+    Outline,
+    /// A wavy underline, as used for spelling errors.
+    WavyUnderline,
+    DottedUnderline,
+    DashedUnderline,
+}
+
+/// A persistent, colored range of text, drawn beneath the text and the selection.
+#[derive(Clone, Debug)]
+pub struct Highlight {
+    pub range: Range<TextLocation>,
+    pub color: Color,
+    pub style: HighlightStyle,
+    /// Highlights with higher z-indices are painted over those with lower ones. Ties are broken
+    /// by the order the highlights were added in.
+    pub z_index: i32,
+}
+
+impl View {
+    /// Adds a highlight and returns an ID that can be used to remove it later.
+    ///
+    /// The highlight's range follows the text it covers as the document is edited.
+    pub fn add_highlight(&mut self, highlight: Highlight) -> HighlightId {
+        let id = HighlightId(self.next_highlight_id);
+        self.next_highlight_id += 1;
+
+        let index = self.highlights
+                        .iter()
+                        .position(|&(_, ref other)| other.z_index > highlight.z_index)
+                        .unwrap_or(self.highlights.len());
+        self.highlights.insert(index, (id, highlight));
+        self.rebuild_display_list();
+        id
+    }
+
+    /// Removes a highlight, returning it if it existed.
+    pub fn remove_highlight(&mut self, id: HighlightId) -> Option<Highlight> {
+        let index = match self.highlights.iter().position(|&(other_id, _)| other_id == id) {
+            None => return None,
+            Some(index) => index,
+        };
+        let (_, highlight) = self.highlights.remove(index);
+        self.rebuild_display_list();
+        Some(highlight)
+    }
+
+    pub fn clear_highlights(&mut self) {
+        if self.highlights.is_empty() {
+            return
+        }
+        self.highlights.clear();
+        self.rebuild_display_list();
+    }
+
+    pub fn highlight(&self, id: HighlightId) -> Option<&Highlight> {
+        self.highlights.iter().find(|&&(other_id, _)| other_id == id).map(|&(_, ref highlight)| {
+            highlight
+        })
+    }
+
+    pub(crate) fn transform_highlights(&mut self, edit: &Edit) {
+        for &mut (_, ref mut highlight) in &mut self.highlights {
+            // Text typed just before a highlight isn't part of it.
+            let start = edit.transform_range_start(&highlight.range.start);
+            let end = cmp::max(edit.transform_location(&highlight.range.end), start);
+            highlight.range = start..end;
+        }
+    }
+}
@@ -0,0 +1,59 @@
+// WRTextView/webrender-text-view/src/inspect.rs
+//
+// Copyright © 2018 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use pilcrow::TextLocation;
+use std::ops::Range;
+use webrender_api::LayoutPoint;
+use {ImageId, View};
+
+/// What lies under a point in the view, for building context menus.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PointInfo {
+    /// The URL of the link under the point, if any.
+    pub url: Option<String>,
+    pub image_id: Option<ImageId>,
+    /// The word under the point, or `None` if the point isn't over text.
+    pub word_range: Option<Range<TextLocation>>,
+    /// Whether the point is inside the current, non-empty selection.
+    pub in_selection: bool,
+}
+
+impl View {
+    /// Describes what is under `point`, which is in view coordinates.
+    pub fn inspect_point(&self, point: &LayoutPoint) -> PointInfo {
+        let mut point_info = PointInfo::default();
+        let (location, formatting) = match self.text_at_point(point) {
+            None => return point_info,
+            Some(text) => text,
+        };
+
+        let paragraph = &self.document.paragraphs()[location.paragraph_index];
+        let char_range = paragraph.word_range_at_char_index(location.character_index);
+        point_info.word_range = Some(TextLocation::new(location.paragraph_index, char_range.start)..
+                                     TextLocation::new(location.paragraph_index, char_range.end));
+
+        point_info.in_selection = match self.selection {
+            None => false,
+            Some(ref selection) => selection.range.start <= location &&
+                location < selection.range.end,
+        };
+
+        for format in formatting {
+            if let Some((_, url)) = format.link() {
+                point_info.url = Some(url.to_string())
+            }
+            if let Some(image_id) = format.image() {
+                point_info.image_id = Some(ImageId(image_id))
+            }
+        }
+
+        point_info
+    }
+}
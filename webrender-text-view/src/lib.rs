@@ -9,68 +9,93 @@
 // except according to those terms.
 
 extern crate app_units;
-extern crate core_text;
 extern crate euclid;
 extern crate gleam;
 extern crate libc;
 extern crate pilcrow;
+extern crate regex;
 extern crate webrender;
 extern crate webrender_api;
 
+#[cfg(any(target_os = "macos", target_os = "ios"))]
+extern crate core_text;
+#[cfg(not(any(target_os = "macos", target_os = "ios")))]
+extern crate fontconfig;
+#[cfg(not(any(target_os = "macos", target_os = "ios")))]
+extern crate freetype;
+
+#[cfg(feature = "headless")]
+extern crate osmesa_sys;
+
 #[macro_use]
 extern crate bitflags;
 #[macro_use]
 extern crate lazy_static;
 
 use app_units::Au;
-use core_text::font as ct_font;
 use euclid::{Length, Point2D, Size2D, Transform2D, TypedScale, TypedSideOffsets2D};
 use euclid::{TypedTransform2D, TypedVector2D};
 use gleam::gl;
 use libc::c_char;
-use pilcrow::{Color, Document, FontFaceId, FontId, Format, Framesetter, LayoutCallbacks};
-use pilcrow::{Section, TextLocation};
-use std::collections::HashMap;
+use pilcrow::{Color, Decoration, Document, FontFaceId, FontId, Format, Framesetter};
+use pilcrow::{LayoutCallbacks, Section, TextLocation};
+use std::cmp;
+use std::collections::{HashMap, HashSet};
 use std::collections::hash_map::Entry;
 use std::f32;
 use std::ffi::CString;
-use std::mem;
 use std::ops::Range;
 use std::os::raw::c_void;
+use std::rc::Rc;
 use std::sync::{Arc, RwLock};
 use std::sync::mpsc::{self, Receiver, Sender};
 use webrender::{DebugFlags, Renderer, RendererOptions};
 use webrender_api::{BuiltDisplayList, ColorF, DeviceIntPoint, DevicePixel, DevicePoint, DeviceUintPoint};
 use webrender_api::{DeviceUintRect, DeviceUintSize, DocumentId, Epoch, FontInstanceKey, FontKey};
 use webrender_api::{IdNamespace, ImageData, ImageDescriptor, ImageFormat, ImageKey, LayoutPoint, LayoutPixel, LayoutRect, LayoutSize, NativeFontHandle};
-use webrender_api::{PipelineId, RenderApi, RenderNotifier, ResourceUpdates, Transaction};
-use webrender_api::{ZoomFactor};
+use webrender_api::{LayoutVector2D, PipelineId, RenderNotifier, ResourceUpdates, Shadow};
+use webrender_api::{Transaction, ZoomFactor};
+
+use resource_api::WrResourceApi;
+use scene_builder::{Caret, FindHighlights, SceneBuilder, SceneState};
+use touch::TouchState;
+use undo::{Edit, HistoryChange, UndoManager};
+
+#[cfg(feature = "headless")]
+use headless::HeadlessContext;
 
-use scene_builder::SceneBuilder;
+pub use find::FindOptions;
+pub use font_backend::{DefaultFontBackend, FontBackend, FontMetrics};
+pub use highlight::{Highlight, HighlightId, HighlightStyle};
+pub use inspect::PointInfo;
+pub use resource_api::{FakeResourceApi, ResourceApi};
+pub use theme::{LinkUnderline, Theme, ThemeColor};
+pub use touch::{LONG_PRESS_DURATION, TouchId};
 
 pub mod ffi;
+mod display_list_dump;
+mod find;
+mod font_backend;
+mod highlight;
+mod inspect;
+mod navigation;
+mod resource_api;
 mod scene_builder;
+mod theme;
+mod touch;
+mod undo;
 
-const DEFAULT_SELECTION_BACKGROUND_COLOR: ColorF = ColorF {
-    r: 0.75,
-    g: 0.75,
-    b: 0.75,
-    a: 1.0,
-};
-
-const DEFAULT_LINK_COLOR: ColorF = ColorF {
-    r: 0.0,
-    g: 0.0,
-    b: 1.0,
-    a: 1.0,
-};
-
-const DEFAULT_LINK_ACTIVE_COLOR: ColorF = ColorF {
-    r: 1.0,
-    g: 0.0,
-    b: 0.0,
-    a: 1.0,
-};
+#[cfg(feature = "headless")]
+mod headless;
+
+#[cfg(any(target_os = "macos", target_os = "ios"))]
+mod core_text_backend;
+#[cfg(not(any(target_os = "macos", target_os = "ios")))]
+mod freetype_backend;
+
+const DEFAULT_CARET_WIDTH: f32 = 1.0;
+const SELECTION_HANDLE_STEM_WIDTH: f32 = 2.0;
+const SELECTION_HANDLE_KNOB_DIAMETER: f32 = 12.0;
 
 pub const PIPELINE_ID: PipelineId = PipelineId(0, 0);
 
@@ -97,31 +122,61 @@ pub(crate) type ImageMap = Arc<RwLock<HashMap<ImageId, ImageInfo>>>;
 bitflags! {
     pub struct ViewFlags: u32 {
         const ENABLE_SUBPIXEL_AA = 0x01;
+        const EDITABLE = 0x02;
+        /// Draw draggable handles at the ends of the selection, for touch input.
+        const SELECTION_HANDLES = 0x04;
+    }
+}
+
+bitflags! {
+    pub struct Modifiers: u32 {
+        const SHIFT = 0x01;
+        const CONTROL = 0x02;
+        const ALT = 0x04;
+        const META = 0x08;
     }
 }
 
 pub struct View {
     document: Document,
+    flags: ViewFlags,
     available_width: Length<f32, LayoutPixel>,
+    content_insets: TypedSideOffsets2D<f32, LayoutPixel>,
     viewport_size: DeviceUintSize,
     device_pixel_ratio: TypedScale<f32, LayoutPixel, DevicePixel>,
     transform: TypedTransform2D<f32, LayoutPixel, LayoutPixel>,
     section: Section,
 
-    selection_background_color: ColorF,
+    theme: Theme,
+    caret_width: f32,
 
     selection: Option<Selection>,
+    focused: bool,
+    caret_visible: bool,
+    goal_inline_position: Option<f32>,
+    marked_range: Option<Range<TextLocation>>,
+    find_query: Option<(String, FindOptions)>,
+    find_matches: Vec<Range<TextLocation>>,
+    current_find_match: Option<usize>,
+    highlights: Vec<(HighlightId, Highlight)>,
+    next_highlight_id: u32,
     active_link_id: Option<LinkId>,
+    hovered_link: Option<(LinkId, String)>,
+    visited_link_ids: HashSet<LinkId>,
     mouse_status: MouseStatus,
+    touch_state: TouchState,
+    undo_manager: UndoManager,
 
     font_keys: FontKeyMap,
+    font_backend: Box<FontBackend>,
     images: ImageMap,
 
-    wr_renderer: Renderer,
-    wr_sender_api: RenderApi,
-    wr_document_id: DocumentId,
+    #[cfg(feature = "headless")]
+    headless_context: Option<HeadlessContext>,
+
+    wr_api: Box<ResourceApi>,
+    wr_renderer: Option<WrRenderer>,
     wr_display_list: WrDisplayList,
-    wr_new_frame_ready_rx: Receiver<()>,
 }
 
 impl View {
@@ -150,7 +205,6 @@ impl View {
             }
         };
 
-        let transform = TypedTransform2D::identity();
         let wr_options = RendererOptions {
             enable_subpixel_aa: flags.contains(ViewFlags::ENABLE_SUBPIXEL_AA),
             ..RendererOptions::default()
@@ -161,58 +215,152 @@ impl View {
         let (renderer, sender) = Renderer::new(gl.clone(), notifier, wr_options).unwrap();
         let sender_api = sender.create_api();
 
-        let available_layout_size = LayoutSize::new(available_width.get(), 1000000.0);
-        let available_device_size = DeviceUintSize::new(available_layout_size.width as u32,
-                                                        available_layout_size.height as u32);
+        let available_device_size = DeviceUintSize::new(available_width.get() as u32, 1000000);
         let document_id = sender_api.add_document(available_device_size, 0);
+
+        let wr_renderer = WrRenderer {
+            renderer,
+            new_frame_ready_rx: wr_new_frame_ready_rx,
+            gl,
+        };
+        View::from_parts(document,
+                         viewport_size,
+                         device_pixel_ratio,
+                         available_width,
+                         Box::new(WrResourceApi::new(sender_api, document_id)),
+                         Some(wr_renderer),
+                         flags)
+    }
+
+    /// Creates a view that sends its resources to `resource_api` and has no renderer.
+    ///
+    /// `repaint()` does nothing on such a view, but everything else works, so this is mainly
+    /// useful for testing with `FakeResourceApi`.
+    pub fn with_resource_api(document: Document,
+                             viewport_size: &DeviceUintSize,
+                             device_pixel_ratio: TypedScale<f32, LayoutPixel, DevicePixel>,
+                             available_width: Length<f32, LayoutPixel>,
+                             resource_api: Box<ResourceApi>,
+                             flags: ViewFlags)
+                             -> View {
+        View::from_parts(document,
+                         viewport_size,
+                         device_pixel_ratio,
+                         available_width,
+                         resource_api,
+                         None,
+                         flags)
+    }
+
+    fn from_parts(document: Document,
+                  viewport_size: &DeviceUintSize,
+                  device_pixel_ratio: TypedScale<f32, LayoutPixel, DevicePixel>,
+                  available_width: Length<f32, LayoutPixel>,
+                  wr_api: Box<ResourceApi>,
+                  wr_renderer: Option<WrRenderer>,
+                  flags: ViewFlags)
+                  -> View {
+        let transform = TypedTransform2D::identity();
+        let available_layout_size = LayoutSize::new(available_width.get(), 1000000.0);
         let mut images = Arc::new(RwLock::new(HashMap::new()));
 
-        let section = layout_text(&document, images.clone(), available_width);
+        let content_insets = TypedSideOffsets2D::zero();
+        let section = layout_text(&document, images.clone(), available_width, &content_insets);
 
         let active_link_id = None;
-        let selection_background_color = DEFAULT_SELECTION_BACKGROUND_COLOR;
+        let theme = Theme::default();
         let mut font_keys = FontKeyMap::new();
-        let mut scene_builder = SceneBuilder::new(&available_layout_size,
-                                                  &None,
-                                                  &active_link_id,
-                                                  &selection_background_color);
+        let font_backend: Box<FontBackend> = Box::new(DefaultFontBackend::new());
+        let visited_link_ids = HashSet::new();
+        let mut scene_builder = SceneBuilder::new(&available_layout_size, SceneState {
+            selection: &None,
+            active_link_id,
+            hovered_link_id: None,
+            visited_link_ids: &visited_link_ids,
+            theme: &theme,
+            focused: true,
+            caret: None,
+            selection_handles: false,
+            marked_range: &None,
+            find_highlights: &None,
+            highlights: &[],
+        });
         let mut resource_updates = ResourceUpdates::new();
-        scene_builder.build_display_list(&sender_api,
+        scene_builder.build_display_list(&*wr_api,
                                          &mut resource_updates,
                                          &mut font_keys,
+                                         &*font_backend,
                                          &mut images,
                                          &section);
         let display_list = scene_builder.finalize();
-
-        let mut transaction = Transaction::new();
-        transaction.update_resources(resource_updates);
-        sender_api.send_transaction(document_id, transaction);
+        wr_api.update_resources(resource_updates);
 
         View {
             document,
+            flags,
             available_width,
+            content_insets,
             device_pixel_ratio,
             viewport_size: *viewport_size,
             transform,
             section,
 
-            selection_background_color,
+            theme,
+            caret_width: DEFAULT_CARET_WIDTH,
 
             selection: None,
+            focused: true,
+            caret_visible: true,
+            goal_inline_position: None,
+            marked_range: None,
+            find_query: None,
+            find_matches: vec![],
+            current_find_match: None,
+            highlights: vec![],
+            next_highlight_id: 0,
             active_link_id,
+            hovered_link: None,
+            visited_link_ids,
             mouse_status: MouseStatus::Up,
+            touch_state: TouchState::new(),
+            undo_manager: UndoManager::new(),
 
             font_keys,
+            font_backend,
             images,
 
-            wr_renderer: renderer,
-            wr_sender_api: sender_api,
-            wr_document_id: document_id,
+            #[cfg(feature = "headless")]
+            headless_context: None,
+
+            wr_api,
+            wr_renderer,
             wr_display_list: display_list,
-            wr_new_frame_ready_rx,
         }
     }
 
+    /// Creates a view that renders offscreen with OSMesa instead of into a host GL context.
+    ///
+    /// Use `render_to_buffer()` to get the pixels.
+    #[cfg(feature = "headless")]
+    pub fn new_headless(document: Document,
+                        viewport_size: &DeviceUintSize,
+                        device_pixel_ratio: TypedScale<f32, LayoutPixel, DevicePixel>,
+                        available_width: Length<f32, LayoutPixel>,
+                        flags: ViewFlags)
+                        -> View {
+        let mut headless_context = HeadlessContext::new();
+        headless_context.make_current(viewport_size);
+        let mut view = View::new(document,
+                                 viewport_size,
+                                 device_pixel_ratio,
+                                 available_width,
+                                 Api::Opengl,
+                                 headless::get_proc_address,
+                                 flags);
+        view.headless_context = Some(headless_context);
+        view
+    }
+
     #[inline]
     pub fn set_debugger_enabled(&mut self, enabled: bool) {
         let flags = if enabled {
@@ -220,22 +368,37 @@ impl View {
         } else {
             DebugFlags::empty()
         };
-        self.wr_renderer.set_debug_flags(flags)
+        if let Some(ref mut wr_renderer) = self.wr_renderer {
+            wr_renderer.renderer.set_debug_flags(flags)
+        }
     }
 
+    /// Returns the size of the document, including the content insets.
     pub fn layout_size(&self) -> LayoutSize {
-        LayoutSize::new(self.available_width.get(), match self.section.frames().last() {
-            None => 0.0,
+        let content_bottom = match self.section.frames().last() {
+            None => self.content_insets.top,
             Some(frame) => {
                 match frame.lines().last() {
-                    None => 0.0,
+                    None => self.content_insets.top,
                     Some(line) => line.origin.y + line.typographic_bounds().descent,
                 }
             }
-        })
+        };
+        LayoutSize::new(self.available_width.get(), content_bottom + self.content_insets.bottom)
     }
 
     pub fn repaint(&mut self) {
+        if self.wr_renderer.is_none() {
+            return
+        }
+
+        #[cfg(feature = "headless")]
+        {
+            if let Some(ref mut headless_context) = self.headless_context {
+                headless_context.make_current(&self.viewport_size)
+            }
+        }
+
         let mut transaction = Transaction::new();
         transaction.set_display_list(Epoch(0),
                                      None,
@@ -252,45 +415,89 @@ impl View {
         transaction.set_pan(DeviceIntPoint::new(pan_vector.x as i32, pan_vector.y as i32));
         transaction.set_pinch_zoom(ZoomFactor::new(self.transform.m11));
         transaction.generate_frame();
-        self.wr_sender_api.send_transaction(self.wr_document_id, transaction);
-        self.wr_new_frame_ready_rx.recv().unwrap();
+        self.wr_api.send_transaction(transaction);
+
+        let wr_renderer = self.wr_renderer.as_mut().unwrap();
+        wr_renderer.new_frame_ready_rx.recv().unwrap();
+        wr_renderer.renderer.update();
+        wr_renderer.renderer.render(self.viewport_size).unwrap();
+    }
+
+    /// Repaints and reads the result back as top-down RGBA rows, `stride` bytes apart.
+    ///
+    /// Returns false if `out_rgba` is too small to hold the viewport or there is no renderer.
+    pub fn render_to_buffer(&mut self, out_rgba: &mut [u8], stride: usize) -> bool {
+        let (width, height) = (self.viewport_size.width as usize,
+                               self.viewport_size.height as usize);
+        if self.wr_renderer.is_none() || stride < width * 4 || out_rgba.len() < stride * height {
+            return false
+        }
+
+        self.repaint();
+
+        // OpenGL returns the rows bottom-up.
+        let gl = &self.wr_renderer.as_ref().unwrap().gl;
+        gl.finish();
+        let pixels = gl.read_pixels(0,
+                                    0,
+                                    width as i32,
+                                    height as i32,
+                                    gl::RGBA,
+                                    gl::UNSIGNED_BYTE);
+        for (y, src_row) in pixels.chunks(width * 4).enumerate() {
+            let dest_start = (height - y - 1) * stride;
+            out_rgba[dest_start..(dest_start + width * 4)].copy_from_slice(src_row);
+        }
+        true
+    }
 
-        self.wr_renderer.update();
-        self.wr_renderer.render(self.viewport_size).unwrap();
+    /// Returns a human-readable JSON dump of the current display list, for debugging.
+    pub fn dump_display_list(&self) -> String {
+        display_list_dump::dump_display_list(&self.wr_display_list.2)
     }
 
     pub fn get_mouse_cursor(&self, point: &LayoutPoint) -> MouseCursor {
-        let HitTestResult {
-            point,
-            frame_index,
-            line_index,
-        } = match self.hit_test_point(point) {
-            None => return MouseCursor::Default,
-            Some(hit_test_result) => hit_test_result,
-        };
-        let frame = &self.section.frames()[frame_index];
-        let lines = frame.lines();
-        let line = &lines[line_index];
-        let char_index = match line.char_index_for_position(&point.to_untyped()) {
-            None => return MouseCursor::Text,
-            Some(char_index) => char_index,
-        };
-        let runs = line.runs();
-        let run = match runs.iter().find(|run| run.char_range().has(char_index)) {
+        if self.hit_test_point(point).is_none() {
+            return MouseCursor::Default
+        }
+        let formatting = match self.text_at_point(point) {
             None => return MouseCursor::Text,
-            Some(run) => run,
+            Some((_, formatting)) => formatting,
         };
-        for format in run.formatting().iter() {
-            if format.link().is_some() {
-                return MouseCursor::Pointer
-            }
+        if formatting.iter().any(|format| format.link().is_some()) {
+            MouseCursor::Pointer
+        } else {
+            MouseCursor::Text
         }
-        MouseCursor::Text
     }
 
-    pub fn mouse_down(&mut self, point: &LayoutPoint, kind: MouseEventKind) {
+    /// Tracks the link under the mouse for hover styling. Call this when the mouse moves with no
+    /// buttons down.
+    pub fn mouse_moved(&mut self, point: &LayoutPoint) -> EventResult {
+        let hovered_link = self.link_at_point(point);
+        self.set_hovered_link(hovered_link)
+    }
+
+    /// Call this when the mouse leaves the view.
+    pub fn mouse_exited(&mut self) -> EventResult {
+        self.set_hovered_link(None)
+    }
+
+    /// Handles a mouse button press. Shift-clicking extends the selection from its anchor.
+    pub fn mouse_down(&mut self, point: &LayoutPoint, kind: MouseEventKind, modifiers: Modifiers)
+                      -> EventResult {
+        // Secondary clicks leave the selection alone, so that the menu can act on it.
+        if kind == MouseEventKind::Right {
+            return EventResult::ContextMenu(self.inspect_point(point))
+        }
+
+        // Clicking commits any input method composition, as it does in native text views.
         let mut active_link_id = None;
-        let mut dirty = false;
+        let mut dirty = self.marked_range.take().is_some();
+        let mut extended = false;
+        let selection_before = self.selection.clone();
+        self.goal_inline_position = None;
+        self.undo_manager.break_coalescing();
 
         if let Some(HitTestResult {
             point,
@@ -301,7 +508,21 @@ impl View {
             let lines = frame.lines();
             let line = &lines[line_index];
             if let Some(char_index) = line.char_index_for_position(&point.to_untyped()) {
-                if kind == MouseEventKind::LeftDouble {
+                let location = TextLocation::new(frame_index, char_index);
+                let extended_selection = match self.selection {
+                    Some(ref selection) if kind == MouseEventKind::Left &&
+                            modifiers.contains(Modifiers::SHIFT) => {
+                        Some(selection.extended_to(location))
+                    }
+                    _ => None,
+                };
+
+                if let Some(extended_selection) = extended_selection {
+                    self.selection = Some(extended_selection);
+                    self.caret_visible = true;
+                    extended = true;
+                    dirty = true;
+                } else if kind == MouseEventKind::LeftDouble {
                     let paragraph = &self.document.paragraphs()[frame_index];
                     let char_range = paragraph.word_range_at_char_index(char_index);
                     let start_location = TextLocation::new(frame_index, char_range.start);
@@ -314,6 +535,10 @@ impl View {
                     let end_location = TextLocation::new(frame_index, paragraph.char_len());
                     self.selection = Some(Selection::forward(start_location..end_location));
                     dirty = true;
+                } else if self.flags.contains(ViewFlags::EDITABLE) {
+                    self.selection = Some(Selection::caret(location));
+                    self.caret_visible = true;
+                    dirty = true;
                 } else if self.selection.is_some() {
                     self.selection = None;
                     dirty = true;
@@ -322,9 +547,12 @@ impl View {
                 let runs = line.runs();
                 if let Some(run) = runs.iter().find(|run| run.char_range().has(char_index)) {
                     for format in run.formatting().iter() {
-                        if let Some((id, _url)) = format.link() {
-                            active_link_id = Some(LinkId(id));
-                            dirty = true;
+                        match format.link() {
+                            Some((id, _url)) if !extended => {
+                                active_link_id = Some(LinkId(id));
+                                dirty = true;
+                            }
+                            _ => {}
                         }
                     }
                 }
@@ -332,6 +560,9 @@ impl View {
         }
 
         self.mouse_status = match kind {
+            // Extending behaves like a drag already in progress, so `mouse_up()` keeps the
+            // selection.
+            _ if extended => MouseStatus::LeftDragSingle,
             MouseEventKind::LeftDouble => MouseStatus::LeftDouble,
             MouseEventKind::LeftTriple => MouseStatus::LeftTriple,
             _ => MouseStatus::LeftSingle,
@@ -341,11 +572,22 @@ impl View {
         if dirty {
             self.rebuild_display_list();
         }
+
+        self.selection_changed_event(&selection_before)
     }
 
-    pub fn mouse_up(&mut self, point: &LayoutPoint, _: MouseEventKind) -> EventResult {
+    /// Handles a mouse button release. Activating a link reports the modifiers held, so that the
+    /// host can open it in a new tab or window.
+    pub fn mouse_up(&mut self, point: &LayoutPoint, kind: MouseEventKind, modifiers: Modifiers)
+                    -> EventResult {
+        if kind == MouseEventKind::Right {
+            return EventResult::None
+        }
+
         let mut event_result = EventResult::None;
         let mut dirty = self.active_link_id.is_some();
+        let selection_before = self.selection.clone();
+        let clicked = self.mouse_status == MouseStatus::LeftSingle;
 
         if let Some(HitTestResult {
             point,
@@ -361,7 +603,13 @@ impl View {
                     for format in run.formatting().iter() {
                         if let Some((link_id, url)) = format.link() {
                             if Some(LinkId(link_id)) == self.active_link_id {
-                                event_result = EventResult::OpenUrl(url.to_string())
+                                self.visited_link_ids.insert(LinkId(link_id));
+                                event_result = EventResult::OpenUrl(url.to_string(), modifiers)
+                            }
+                        }
+                        if let Some(image_id) = format.image() {
+                            if clicked && event_result.is_none() {
+                                event_result = EventResult::ImageClicked(ImageId(image_id))
                             }
                         }
                     }
@@ -369,7 +617,9 @@ impl View {
             }
         }
 
-        if self.mouse_status == MouseStatus::LeftSingle {
+        // Editable views keep the caret that `mouse_down()` placed.
+        if self.mouse_status == MouseStatus::LeftSingle &&
+                !self.flags.contains(ViewFlags::EDITABLE) {
             self.selection = None;
             dirty = true;
         }
@@ -380,10 +630,11 @@ impl View {
             self.rebuild_display_list();
         }
 
-        event_result
+        event_result.or(self.selection_changed_event(&selection_before))
     }
 
-    pub fn mouse_dragged(&mut self, point: &LayoutPoint) {
+    pub fn mouse_dragged(&mut self, point: &LayoutPoint) -> EventResult {
+        let selection_before = self.selection.clone();
         if let Some(HitTestResult {
             point,
             frame_index,
@@ -469,6 +720,7 @@ impl View {
         self.active_link_id = None;
 
         self.rebuild_display_list();
+        self.selection_changed_event(&selection_before)
     }
 
     pub fn available_width(&self) -> Length<f32, LayoutPixel> {
@@ -480,11 +732,50 @@ impl View {
         self.layout();
     }
 
+    #[inline]
+    pub fn content_insets(&self) -> TypedSideOffsets2D<f32, LayoutPixel> {
+        self.content_insets
+    }
+
+    /// Sets the margins between the edges of the view and the text.
+    pub fn set_content_insets(&mut self, content_insets: &TypedSideOffsets2D<f32, LayoutPixel>) {
+        self.content_insets = *content_insets;
+        self.layout();
+    }
+
     pub fn set_translation(&mut self, origin: &TypedVector2D<f32, LayoutPixel>) {
         self.transform.m31 = -origin.x;
         self.transform.m32 = -origin.y;
     }
 
+    /// Returns the scroll origin, as set by `set_translation` or by scrolling to reveal
+    /// something. Hosts should sync their scrollers to this.
+    #[inline]
+    pub fn translation(&self) -> TypedVector2D<f32, LayoutPixel> {
+        TypedVector2D::new(-self.transform.m31, -self.transform.m32)
+    }
+
+    /// Scrolls vertically as little as possible to make `rect`, in document coordinates,
+    /// visible.
+    pub fn scroll_rect_to_visible(&mut self, rect: &LayoutRect) {
+        let scale = self.transform.m22;
+        let visible_height = self.visible_height();
+        let visible_top = -self.transform.m32 / scale;
+        let top = if rect.origin.y < visible_top {
+            rect.origin.y
+        } else if rect.max_y() > visible_top + visible_height {
+            rect.max_y() - visible_height
+        } else {
+            return
+        };
+        self.transform.m32 = -top * scale;
+    }
+
+    #[inline]
+    pub fn viewport_size(&self) -> DeviceUintSize {
+        self.viewport_size
+    }
+
     pub fn set_viewport_size(&mut self, viewport_size: &DeviceUintSize) {
         self.viewport_size = *viewport_size
     }
@@ -494,26 +785,82 @@ impl View {
         self.transform.m22 = factor;
     }
 
+    #[inline]
+    pub fn scale(&self) -> f32 {
+        self.transform.m11
+    }
+
+    #[inline]
+    pub fn theme(&self) -> &Theme {
+        &self.theme
+    }
+
+    /// Replaces every theme color at once.
+    pub fn set_theme(&mut self, theme: Theme) {
+        self.theme = theme;
+        self.rebuild_display_list();
+    }
+
+    /// Sets the color painted behind the document.
+    pub fn set_page_background_color(&mut self, color: Color) {
+        self.theme.page_background_color = color;
+        self.rebuild_display_list();
+    }
+
+    /// Tells the view whether its window has keyboard focus. Unfocused views draw the selection
+    /// in the inactive selection color and hide the caret.
+    pub fn set_focused(&mut self, focused: bool) {
+        if self.focused == focused {
+            return
+        }
+        self.focused = focused;
+        self.rebuild_display_list();
+    }
+
+    pub fn set_inactive_selection_background_color(&mut self, color: Color) {
+        self.theme.inactive_selection_background_color = color;
+        if !self.focused {
+            self.rebuild_display_list()
+        }
+    }
+
     pub fn set_selection_background_color(&mut self, color: Color) {
-        self.selection_background_color = color.to_colorf()
+        self.theme.selection_background_color = color
     }
 
-    pub fn select_all(&mut self) {
-        {
-            let paragraphs = self.document.paragraphs();
-            let paragraph_index = match paragraphs.len() {
-                0 => 0,
-                paragraph_count => paragraph_count - 1,
-            };
-            let character_index = match paragraphs.get(paragraph_index) {
-                None => 0,
-                Some(paragraph) => paragraph.char_len(),
-            };
-            let end = TextLocation::new(paragraph_index, character_index);
-            self.selection = Some(Selection::forward(TextLocation::beginning()..end));
+    pub fn set_caret_color(&mut self, color: Color) {
+        self.theme.caret_color = color;
+        self.rebuild_display_list_if_caret_shown();
+    }
+
+    pub fn set_caret_width(&mut self, width: f32) {
+        self.caret_width = width;
+        self.rebuild_display_list_if_caret_shown();
+    }
+
+    /// Returns true if this is a focused, editable view with a caret, so the host should run its
+    /// blink timer.
+    #[inline]
+    pub fn has_caret(&self) -> bool {
+        self.focused && self.flags.contains(ViewFlags::EDITABLE) &&
+            self.selection.as_ref().map_or(false, |selection| selection.is_collapsed())
+    }
+
+    /// Shows or hides the caret. The host calls this from its blink timer.
+    pub fn set_caret_blink_phase(&mut self, visible: bool) {
+        if self.caret_visible == visible {
+            return
         }
+        self.caret_visible = visible;
+        self.rebuild_display_list_if_caret_shown();
+    }
 
+    pub fn select_all(&mut self) -> EventResult {
+        let selection_before = self.selection.clone();
+        let end = self.end_location();
+        self.selection = Some(Selection::forward(TextLocation::beginning()..end));
         self.rebuild_display_list();
+        self.selection_changed_event(&selection_before)
     }
 
     #[inline]
@@ -523,9 +870,144 @@ impl View {
 
     #[inline]
     pub fn document_changed(&mut self) {
+        self.undo_manager.clear();
+        self.refresh_find_matches();
         self.layout()
     }
 
+    /// Inserts `text` at `location` and leaves the caret after it.
+    ///
+    /// Newlines in `text` split paragraphs. Locations past the end of a paragraph or of the
+    /// document are clamped to it. Does nothing unless the view is editable.
+    pub fn insert_text(&mut self, location: &TextLocation, text: &str) -> EventResult {
+        let location = self.clamp_location(location);
+        self.replace_range(location..location, text, false)
+    }
+
+    /// Deletes the text in `range` and leaves the caret where the range started.
+    ///
+    /// The range is clamped to the document. Does nothing unless the view is editable.
+    pub fn delete_range(&mut self, range: Range<TextLocation>) -> EventResult {
+        let (start, end) = (self.clamp_location(&range.start), self.clamp_location(&range.end));
+        if start <= end {
+            self.replace_range(start..end, "", false)
+        } else {
+            self.replace_range(end..start, "", false)
+        }
+    }
+
+    /// Adds `format` to the text in `range`, as an undoable edit. The selection is left alone.
+    ///
+    /// The range is clamped to the document. Does nothing unless the view is editable.
+    pub fn add_format(&mut self, range: Range<TextLocation>, format: Format) -> EventResult {
+        let (start, end) = (self.clamp_location(&range.start), self.clamp_location(&range.end));
+        if !self.flags.contains(ViewFlags::EDITABLE) || start >= end {
+            return EventResult::None
+        }
+
+        let edit = Edit::add_format(&mut self.document, start..end, format);
+        let selection = self.selection.clone();
+        self.layout();
+        self.undo_manager.record(edit, selection, false);
+        EventResult::DocumentEdited
+    }
+
+    /// Deletes the selected text, or the character before the caret if the selection is empty.
+    pub fn delete_backward(&mut self) -> EventResult {
+        let range = match self.selection {
+            None => return EventResult::None,
+            Some(ref selection) if !selection.is_collapsed() => selection.range.clone(),
+            Some(ref selection) => {
+                match self.previous_location(&selection.range.start) {
+                    None => return EventResult::None,
+                    Some(previous_location) => previous_location..selection.range.start,
+                }
+            }
+        };
+        self.replace_range(range, "", true)
+    }
+
+    /// Deletes the selected text, or the character after the caret if the selection is empty.
+    pub fn delete_forward(&mut self) -> EventResult {
+        let range = match self.selection {
+            None => return EventResult::None,
+            Some(ref selection) if !selection.is_collapsed() => selection.range.clone(),
+            Some(ref selection) => {
+                match self.next_location(&selection.range.end) {
+                    None => return EventResult::None,
+                    Some(next_location) => selection.range.end..next_location,
+                }
+            }
+        };
+        self.replace_range(range, "", true)
+    }
+
+    /// Replaces the selected text with `text`, as typing does.
+    ///
+    /// If an input method composition is in progress, `text` replaces it instead.
+    pub fn replace_selection(&mut self, text: &str) -> EventResult {
+        let range = match (&self.marked_range, &self.selection) {
+            (&Some(ref marked_range), _) => marked_range.clone(),
+            (&None, &Some(ref selection)) => selection.range.clone(),
+            (&None, &None) => return EventResult::None,
+        };
+        self.replace_range(range, text, true)
+    }
+
+    /// Sets the provisional text of an input method composition, replacing the previous
+    /// composition or, if there isn't one, the selection.
+    ///
+    /// `selected_range` is the range of characters within `text` to select; if it's empty, the
+    /// caret is placed there instead.
+    pub fn set_marked_text(&mut self, text: &str, selected_range: Range<usize>) -> EventResult {
+        let range = match (&self.marked_range, &self.selection) {
+            (&Some(ref marked_range), _) => marked_range.clone(),
+            (&None, &Some(ref selection)) => selection.range.clone(),
+            (&None, &None) => return EventResult::None,
+        };
+        // Read-only views insert nothing, so there is no composition to mark.
+        let event_result = self.replace_range(range.clone(), text, true);
+        if event_result.is_none() || text.is_empty() {
+            return event_result
+        }
+
+        let selection_start: String = text.chars().take(selected_range.start).collect();
+        let selection_end: String = text.chars().take(selected_range.end).collect();
+        let selection_start = location_after_text(&range.start, &selection_start);
+        let selection_end = location_after_text(&range.start, &selection_end);
+        self.selection = Some(Selection::forward(selection_start..selection_end));
+        self.marked_range = Some(range.start..location_after_text(&range.start, text));
+        self.rebuild_display_list();
+        event_result
+    }
+
+    /// Commits the input method composition, leaving its text in the document. Reports
+    /// `DocumentEdited` if there was a composition, since its text is now final.
+    pub fn unmark_text(&mut self) -> EventResult {
+        if self.marked_range.take().is_none() {
+            return EventResult::None
+        }
+        self.rebuild_display_list();
+        EventResult::DocumentEdited
+    }
+
+    #[inline]
+    pub fn has_marked_text(&self) -> bool {
+        self.marked_range.is_some()
+    }
+
+    /// Returns the bounds of the caret, or of the moving end of the selection, in view
+    /// coordinates. Hosts use this to position input method candidate windows.
+    pub fn caret_rect(&self) -> Option<LayoutRect> {
+        let (location, affinity) = match self.selection {
+            None => return None,
+            Some(ref selection) => (selection.focus(), selection.affinity),
+        };
+        self.section.caret_bounds(&location, affinity, self.caret_width).map(|caret_bounds| {
+            self.transform.transform_rect(&caret_bounds)
+        })
+    }
+
     pub fn copy_selected_text(&self) -> Option<String> {
         self.selection.as_ref().map(|selection| {
             self.document.copy_string_in_range(selection.range.clone())
@@ -562,9 +1044,128 @@ impl View {
         self.rebuild_display_list();
     }
 
+    pub fn undo(&mut self) -> EventResult {
+        match self.undo_manager.undo(&mut self.document) {
+            None => EventResult::None,
+            Some(history_change) => self.apply_history_change(history_change),
+        }
+    }
+
+    pub fn redo(&mut self) -> EventResult {
+        match self.undo_manager.redo(&mut self.document) {
+            None => EventResult::None,
+            Some(history_change) => self.apply_history_change(history_change),
+        }
+    }
+
+    #[inline]
+    pub fn can_undo(&self) -> bool {
+        self.undo_manager.can_undo()
+    }
+
+    #[inline]
+    pub fn can_redo(&self) -> bool {
+        self.undo_manager.can_redo()
+    }
+
+    fn apply_history_change(&mut self, history_change: HistoryChange) -> EventResult {
+        for edit in &history_change.applied_edits {
+            self.transform_highlights(edit)
+        }
+        self.selection = history_change.selection;
+        self.marked_range = None;
+        self.caret_visible = true;
+        self.goal_inline_position = None;
+        self.refresh_find_matches();
+        self.layout();
+        EventResult::DocumentEdited
+    }
+
+    // `coalesce` allows the edit to be undone together with the previous one; see
+    // `UndoManager::record`.
+    fn replace_range(&mut self, range: Range<TextLocation>, text: &str, coalesce: bool)
+                     -> EventResult {
+        if !self.flags.contains(ViewFlags::EDITABLE) ||
+                (range.start == range.end && text.is_empty()) {
+            return EventResult::None
+        }
+
+        self.marked_range = None;
+        let edit = Edit::replace_text(&mut self.document, range, text);
+
+        let selection_before = self.selection.take();
+        self.selection = Some(Selection::caret(edit.inserted_end()));
+        self.transform_highlights(&edit);
+        self.caret_visible = true;
+        self.goal_inline_position = None;
+        self.refresh_find_matches();
+        self.layout();
+        self.undo_manager.record(edit, selection_before, coalesce);
+        EventResult::DocumentEdited
+    }
+
+    fn previous_location(&self, location: &TextLocation) -> Option<TextLocation> {
+        if location.character_index > 0 {
+            return Some(TextLocation::new(location.paragraph_index, location.character_index - 1))
+        }
+        if location.paragraph_index == 0 {
+            return None
+        }
+        let paragraph_index = location.paragraph_index - 1;
+        let paragraph = &self.document.paragraphs()[paragraph_index];
+        Some(TextLocation::new(paragraph_index, paragraph.char_len()))
+    }
+
+    fn next_location(&self, location: &TextLocation) -> Option<TextLocation> {
+        let paragraphs = self.document.paragraphs();
+        let paragraph = match paragraphs.get(location.paragraph_index) {
+            None => return None,
+            Some(paragraph) => paragraph,
+        };
+        if location.character_index < paragraph.char_len() {
+            return Some(TextLocation::new(location.paragraph_index, location.character_index + 1))
+        }
+        if location.paragraph_index + 1 >= paragraphs.len() {
+            return None
+        }
+        Some(TextLocation::new(location.paragraph_index + 1, 0))
+    }
+
+    fn end_location(&self) -> TextLocation {
+        let paragraphs = self.document.paragraphs();
+        let paragraph_index = match paragraphs.len() {
+            0 => 0,
+            paragraph_count => paragraph_count - 1,
+        };
+        let character_index = match paragraphs.get(paragraph_index) {
+            None => 0,
+            Some(paragraph) => paragraph.char_len(),
+        };
+        TextLocation::new(paragraph_index, character_index)
+    }
+
+    // Moves `location` back to the end of its paragraph, or of the document, if it lies beyond.
+    fn clamp_location(&self, location: &TextLocation) -> TextLocation {
+        let end = self.end_location();
+        if self.document.paragraphs().is_empty() || location.paragraph_index > end.paragraph_index {
+            return end
+        }
+        let paragraph = &self.document.paragraphs()[location.paragraph_index];
+        TextLocation::new(location.paragraph_index,
+                          cmp::min(location.character_index, paragraph.char_len()))
+    }
+
+    // The height of the viewport, in document coordinates.
+    fn visible_height(&self) -> f32 {
+        self.viewport_size.height as f32 / self.device_pixel_ratio.get() / self.transform.m22
+    }
+
     fn layout(&mut self) {
         let available_width = self.available_width;
-        self.section = layout_text(&self.document, self.images.clone(), available_width);
+        self.section = layout_text(&self.document,
+                                   self.images.clone(),
+                                   available_width,
+                                   &self.content_insets);
 
         self.rebuild_display_list();
     }
@@ -572,21 +1173,113 @@ impl View {
     fn rebuild_display_list(&mut self) {
         let available_layout_size = LayoutSize::new(self.available_width.get(), 1000000.0);
         let selected_range = self.selection.as_ref().map(|selection| selection.range.clone());
-        let mut scene_builder = SceneBuilder::new(&available_layout_size,
-                                                  &selected_range,
-                                                  &self.active_link_id,
-                                                  &self.selection_background_color);
+        let caret = if self.caret_visible && self.focused &&
+                self.flags.contains(ViewFlags::EDITABLE) {
+            Some(Caret {
+                color: self.theme.caret_color.to_colorf(),
+                width: self.caret_width,
+                affinity: self.selection.as_ref().map_or(CaretAffinity::Downstream,
+                                                         |selection| selection.affinity),
+            })
+        } else {
+            None
+        };
+        let highlights: Vec<Highlight> =
+            self.highlights.iter().map(|&(_, ref highlight)| highlight.clone()).collect();
+        let find_highlights = if self.find_matches.is_empty() {
+            None
+        } else {
+            Some(FindHighlights {
+                matches: self.find_matches.clone(),
+                current_match: self.current_find_match,
+            })
+        };
+        let hovered_link_id = self.hovered_link.as_ref().map(|&(link_id, _)| link_id);
+        let mut scene_builder = SceneBuilder::new(&available_layout_size, SceneState {
+            selection: &selected_range,
+            active_link_id: self.active_link_id,
+            hovered_link_id,
+            visited_link_ids: &self.visited_link_ids,
+            theme: &self.theme,
+            focused: self.focused,
+            caret,
+            selection_handles: self.flags.contains(ViewFlags::SELECTION_HANDLES),
+            marked_range: &self.marked_range,
+            find_highlights: &find_highlights,
+            highlights: &highlights,
+        });
         let mut resource_updates = ResourceUpdates::new();
-        scene_builder.build_display_list(&self.wr_sender_api,
+        scene_builder.build_display_list(&*self.wr_api,
                                          &mut resource_updates,
                                          &mut self.font_keys,
+                                         &*self.font_backend,
                                          &mut self.images,
                                          &self.section);
         self.wr_display_list = scene_builder.finalize();
+        self.wr_api.update_resources(resource_updates);
+    }
 
-        let mut transaction = Transaction::new();
-        transaction.update_resources(resource_updates);
-        self.wr_sender_api.send_transaction(self.wr_document_id, transaction);
+    fn set_hovered_link(&mut self, hovered_link: Option<(LinkId, String)>) -> EventResult {
+        if self.hovered_link == hovered_link {
+            return EventResult::None
+        }
+        let event_result = match (&hovered_link, &self.hovered_link) {
+            (&Some((_, ref url)), _) => EventResult::LinkHoverEnter(url.clone()),
+            (&None, &Some((_, ref url))) => EventResult::LinkHoverLeave(url.clone()),
+            (&None, &None) => EventResult::None,
+        };
+        self.hovered_link = hovered_link;
+        self.rebuild_display_list();
+        event_result
+    }
+
+    fn selection_changed_event(&self, selection_before: &Option<Selection>) -> EventResult {
+        if self.selection == *selection_before {
+            EventResult::None
+        } else {
+            EventResult::SelectionChanged
+        }
+    }
+
+    fn link_at_point(&self, point: &LayoutPoint) -> Option<(LinkId, String)> {
+        let formatting = match self.text_at_point(point) {
+            None => return None,
+            Some((_, formatting)) => formatting,
+        };
+        formatting.into_iter().filter_map(|format| {
+            format.link().map(|(link_id, url)| (LinkId(link_id), url.to_string()))
+        }).next()
+    }
+
+    // Returns the location of the character under `point` and the formatting of its run.
+    fn text_at_point(&self, point: &LayoutPoint) -> Option<(TextLocation, Vec<Format>)> {
+        let HitTestResult {
+            point,
+            frame_index,
+            line_index,
+        } = match self.hit_test_point(point) {
+            None => return None,
+            Some(hit_test_result) => hit_test_result,
+        };
+        let frame = &self.section.frames()[frame_index];
+        let lines = frame.lines();
+        let line = &lines[line_index];
+        let char_index = match line.char_index_for_position(&point.to_untyped()) {
+            None => return None,
+            Some(char_index) => char_index,
+        };
+        let runs = line.runs();
+        let formatting = match runs.iter().find(|run| run.char_range().has(char_index)) {
+            None => vec![],
+            Some(run) => run.formatting(),
+        };
+        Some((TextLocation::new(frame_index, char_index), formatting))
+    }
+
+    fn rebuild_display_list_if_caret_shown(&mut self) {
+        if self.has_caret() {
+            self.rebuild_display_list()
+        }
     }
 
     fn hit_test_point(&self, point: &LayoutPoint) -> Option<HitTestResult> {
@@ -631,10 +1324,26 @@ impl View {
                                                         true),
                                    ImageData::Raw(image.data.clone()),
                                    None);
+        self.wr_api.update_resources(resource_updates);
+    }
+}
 
-        let mut transaction = Transaction::new();
-        transaction.update_resources(resource_updates);
-        self.wr_sender_api.send_transaction(self.wr_document_id, transaction);
+impl Drop for View {
+    fn drop(&mut self) {
+        let wr_renderer = match self.wr_renderer.take() {
+            None => return,
+            Some(wr_renderer) => wr_renderer,
+        };
+
+        // The renderer has to free its GL resources while its context is still alive and current.
+        #[cfg(feature = "headless")]
+        {
+            if let Some(ref mut headless_context) = self.headless_context {
+                headless_context.make_current(&self.viewport_size)
+            }
+        }
+
+        wr_renderer.renderer.deinit();
     }
 }
 
@@ -646,12 +1355,24 @@ pub enum MouseCursor {
     Pointer,
 }
 
-fn layout_text(text: &Document, images: ImageMap, available_width: Length<f32, LayoutPixel>)
+/// Returns the location just past `text` if it were inserted at `location`.
+fn location_after_text(location: &TextLocation, text: &str) -> TextLocation {
+    let mut lines = text.split('\n');
+    let mut end = *location;
+    end.character_index += lines.next().unwrap().chars().count();
+    for line in lines {
+        end = TextLocation::new(end.paragraph_index + 1, line.chars().count());
+    }
+    end
+}
+
+fn layout_text(text: &Document,
+               images: ImageMap,
+               available_width: Length<f32, LayoutPixel>,
+               content_insets: &TypedSideOffsets2D<f32, LayoutPixel>)
                -> Section {
     let framesetter = Framesetter::new(text);
-    let layout_origin = LayoutPoint::zero();
-    let layout_size = LayoutSize::new(available_width.get(), 1000000.0);
-    let layout_rect = LayoutRect::new(layout_origin, layout_size).to_untyped();
+    let layout_rect = layout_rect(available_width, content_insets).to_untyped();
     let helper = Box::new(LayoutHelper {
         images,
     });
@@ -659,6 +1380,21 @@ fn layout_text(text: &Document, images: ImageMap, available_width: Length<f32, L
     framesetter.layout_in_rect(&layout_rect, Some(helper))
 }
 
+fn layout_rect(available_width: Length<f32, LayoutPixel>,
+               content_insets: &TypedSideOffsets2D<f32, LayoutPixel>)
+               -> LayoutRect {
+    let layout_origin = LayoutPoint::new(content_insets.left, content_insets.top);
+    let content_width = available_width.get() - content_insets.left - content_insets.right;
+    let layout_size = LayoutSize::new(f32::max(content_width, 0.0), 1000000.0);
+    LayoutRect::new(layout_origin, layout_size)
+}
+
+struct WrRenderer {
+    renderer: Renderer,
+    new_frame_ready_rx: Receiver<()>,
+    gl: Rc<gl::Gl>,
+}
+
 struct Notifier {
     tx: Sender<()>,
 }
@@ -686,22 +1422,37 @@ impl RenderNotifier for Notifier {
 pub(crate) struct ComputedStyle {
     font: Option<(FontFaceId, FontId)>,
     color: Option<ColorF>,
+    background_color: Option<ColorF>,
     image: Option<ImageId>,
-    underline: bool,
+    decorations: Vec<ComputedDecoration>,
+    shadow: Option<Shadow>,
+}
+
+/// A text decoration line. If `color` is `None`, the line is drawn in the text color.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct ComputedDecoration {
+    kind: Decoration,
+    color: Option<ColorF>,
 }
 
 impl ComputedStyle {
     pub fn from_formatting(formatting: Vec<Format>,
                            active_link_id: &Option<LinkId>,
+                           hovered_link_id: &Option<LinkId>,
+                           visited_link_ids: &HashSet<LinkId>,
+                           theme: &Theme,
                            font_keys: &mut FontKeyMap,
-                           render_api: &RenderApi,
+                           font_backend: &FontBackend,
+                           render_api: &ResourceApi,
                            resource_updates: &mut ResourceUpdates)
                            -> ComputedStyle {
         let mut computed_style = ComputedStyle {
             font: None,
             color: None,
+            background_color: None,
             image: None,
-            underline: false,
+            decorations: vec![],
+            shadow: None,
         };
 
         for format in formatting.into_iter().rev() {
@@ -709,11 +1460,7 @@ impl ComputedStyle {
                 if computed_style.font.is_none() {
                     let font_info = font_keys.entry(font.face_id()).or_insert_with(|| {
                         let font_key = render_api.generate_font_key();
-                        // FIXME(pcwalton): Workaround for version mismatch of `core-graphics`!
-                        let native_handle = unsafe {
-                            NativeFontHandle(mem::transmute(font.native_font()
-                                                                .copy_to_CGFont()))
-                        };
+                        let native_handle = font_backend.native_font_handle(&font);
                         resource_updates.add_native_font(font_key, native_handle.clone());
                         FontInfo {
                             key: font_key,
@@ -732,16 +1479,14 @@ impl ComputedStyle {
                                                            None,
                                                            None,
                                                            vec![]);
-                        // FIXME(pcwalton): Another workaround for version mismatch of
-                        // `core-graphics`!
-                        let ct_font = unsafe {
-                            ct_font::new_from_CGFont(mem::transmute(&native_font_handle.0),
-                                                     font.size() as f64)
-                        };
+                        let font_metrics = font_backend.font_metrics(&native_font_handle,
+                                                                     font.size());
                         entry.insert(FontInstanceInfo {
                             key: font_instance_key,
-                            underline_position: ct_font.underline_position() as f32,
-                            underline_thickness: ct_font.underline_thickness() as f32,
+                            underline_position: font_metrics.underline_position,
+                            underline_thickness: font_metrics.underline_thickness,
+                            strikeout_position: font_metrics.strikeout_position,
+                            strikeout_thickness: font_metrics.strikeout_thickness,
                         });
                     }
                     computed_style.font = Some((font.face_id(), font.id()))
@@ -754,15 +1499,48 @@ impl ComputedStyle {
                 }
             }
 
+            if let Some(background_color) = format.background_color() {
+                if computed_style.background_color.is_none() {
+                    computed_style.background_color = Some(background_color.to_colorf())
+                }
+            }
+
+            if let Some(shadow) = format.shadow() {
+                if computed_style.shadow.is_none() {
+                    computed_style.shadow = Some(Shadow {
+                        offset: LayoutVector2D::from_untyped(&shadow.offset),
+                        color: shadow.color.to_colorf(),
+                        blur_radius: shadow.blur_radius,
+                    })
+                }
+            }
+
+            if let Some((decoration, color)) = format.decoration() {
+                computed_style.add_decoration(decoration, color.map(|color| color.to_colorf()))
+            }
+
             if let Some((link_id, _)) = format.link() {
-                computed_style.underline = true;
+                let link_id = LinkId(link_id);
+                let hovered = *hovered_link_id == Some(link_id);
+                let underlined = match theme.link_underline {
+                    LinkUnderline::Always => true,
+                    LinkUnderline::Hover => hovered,
+                    LinkUnderline::Never => false,
+                };
+                if underlined {
+                    computed_style.add_decoration(Decoration::Underline, None);
+                }
                 if computed_style.color.is_none() {
-                    let color = if *active_link_id == Some(LinkId(link_id)) {
-                        DEFAULT_LINK_ACTIVE_COLOR
+                    let color = if *active_link_id == Some(link_id) {
+                        theme.active_link_color
+                    } else if hovered {
+                        theme.hovered_link_color
+                    } else if visited_link_ids.contains(&link_id) {
+                        theme.visited_link_color
                     } else {
-                        DEFAULT_LINK_COLOR
+                        theme.link_color
                     };
-                    computed_style.color = Some(color)
+                    computed_style.color = Some(color.to_colorf())
                 }
             }
 
@@ -775,6 +1553,16 @@ impl ComputedStyle {
 
         computed_style
     }
+
+    // Inner formats come first, so they win if the same decoration is applied more than once.
+    fn add_decoration(&mut self, kind: Decoration, color: Option<ColorF>) {
+        if self.decorations.iter().all(|decoration| decoration.kind != kind) {
+            self.decorations.push(ComputedDecoration {
+                kind,
+                color,
+            })
+        }
+    }
 }
 
 pub struct FontInfo {
@@ -787,6 +1575,97 @@ struct FontInstanceInfo {
     key: FontInstanceKey,
     underline_position: f32,
     underline_thickness: f32,
+    strikeout_position: f32,
+    strikeout_thickness: f32,
+}
+
+pub(crate) trait SectionExt {
+    fn line_index_for_location(&self, location: &TextLocation, affinity: CaretAffinity)
+                               -> Option<usize>;
+    fn caret_bounds(&self, location: &TextLocation, affinity: CaretAffinity, caret_width: f32)
+                    -> Option<LayoutRect>;
+    fn selection_handle_bounds(&self, location: &TextLocation, handle: SelectionHandle)
+                               -> Option<SelectionHandleBounds>;
+}
+
+impl SectionExt for Section {
+    fn line_index_for_location(&self, location: &TextLocation, affinity: CaretAffinity)
+                               -> Option<usize> {
+        let frame = match self.frames().get(location.paragraph_index) {
+            None => return None,
+            Some(frame) => frame,
+        };
+        let lines = frame.lines();
+        let character_index = location.character_index;
+        let line_index = match affinity {
+            CaretAffinity::Downstream => {
+                lines.iter().position(|line| character_index < line.char_range().end)
+            }
+            CaretAffinity::Upstream => {
+                lines.iter().position(|line| character_index <= line.char_range().end)
+            }
+        };
+        line_index.or_else(|| lines.len().checked_sub(1))
+    }
+
+    fn caret_bounds(&self, location: &TextLocation, affinity: CaretAffinity, caret_width: f32)
+                    -> Option<LayoutRect> {
+        let line_index = match self.line_index_for_location(location, affinity) {
+            None => return None,
+            Some(line_index) => line_index,
+        };
+        let line = &self.frames()[location.paragraph_index].lines()[line_index];
+        let typo_bounds = line.typographic_bounds();
+        let inline_position = line.inline_position_for_char_index(location.character_index);
+        let origin = LayoutPoint::new(line.origin.x + inline_position,
+                                      line.origin.y - typo_bounds.ascent);
+        let size = LayoutSize::new(caret_width, typo_bounds.ascent + typo_bounds.descent);
+        Some(LayoutRect::new(origin, size))
+    }
+
+    // The knob sits above the start of the selection and below its end, so that the two handles
+    // point away from each other.
+    fn selection_handle_bounds(&self, location: &TextLocation, handle: SelectionHandle)
+                               -> Option<SelectionHandleBounds> {
+        let stem = match self.caret_bounds(location,
+                                           CaretAffinity::Downstream,
+                                           SELECTION_HANDLE_STEM_WIDTH) {
+            None => return None,
+            Some(stem) => stem,
+        };
+        let knob_size = LayoutSize::new(SELECTION_HANDLE_KNOB_DIAMETER,
+                                        SELECTION_HANDLE_KNOB_DIAMETER);
+        let knob_x = stem.origin.x + (SELECTION_HANDLE_STEM_WIDTH -
+                                      SELECTION_HANDLE_KNOB_DIAMETER) * 0.5;
+        let knob_y = match handle {
+            SelectionHandle::Start => stem.origin.y - SELECTION_HANDLE_KNOB_DIAMETER,
+            SelectionHandle::End => stem.max_y(),
+        };
+        Some(SelectionHandleBounds {
+            stem,
+            knob: LayoutRect::new(LayoutPoint::new(knob_x, knob_y), knob_size),
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum SelectionHandle {
+    Start,
+    End,
+}
+
+/// The parts of a selection handle, in document coordinates.
+#[derive(Clone, Copy, Debug)]
+struct SelectionHandleBounds {
+    stem: LayoutRect,
+    knob: LayoutRect,
+}
+
+impl SelectionHandleBounds {
+    #[inline]
+    fn union(&self) -> LayoutRect {
+        self.stem.union(&self.knob)
+    }
 }
 
 trait ColorExt {
@@ -822,10 +1701,67 @@ struct HitTestResult {
     line_index: usize,
 }
 
-#[derive(Clone, Debug)]
+/// What happened in response to an input event, for the host to act on.
+///
+/// Each entry point reports at most one event. When several happen at once, edits and link or
+/// image activations win over selection changes, which win over hover changes. Hovering from one
+/// link directly onto another reports only `LinkHoverEnter`.
+#[derive(Clone, Debug, PartialEq)]
 pub enum EventResult {
     None,
-    OpenUrl(String),
+    /// A link was activated, with the modifiers held when the mouse button was released.
+    OpenUrl(String, Modifiers),
+    SelectionChanged,
+    LinkHoverEnter(String),
+    LinkHoverLeave(String),
+    ImageClicked(ImageId),
+    /// A secondary click, with what is under the pointer.
+    ContextMenu(PointInfo),
+    DocumentEdited,
+    /// A touch gesture panned or zoomed the view.
+    TransformChanged,
+}
+
+impl EventResult {
+    #[inline]
+    pub fn is_none(&self) -> bool {
+        *self == EventResult::None
+    }
+
+    /// The URL of the link this event concerns, if any.
+    pub fn url(&self) -> Option<&str> {
+        match *self {
+            EventResult::OpenUrl(ref url, _) |
+            EventResult::LinkHoverEnter(ref url) |
+            EventResult::LinkHoverLeave(ref url) => Some(url),
+            EventResult::ContextMenu(ref point_info) => {
+                point_info.url.as_ref().map(|url| &**url)
+            }
+            EventResult::None |
+            EventResult::SelectionChanged |
+            EventResult::ImageClicked(_) |
+            EventResult::DocumentEdited |
+            EventResult::TransformChanged => None,
+        }
+    }
+
+    /// The image this event concerns, if any.
+    pub fn image_id(&self) -> Option<ImageId> {
+        match *self {
+            EventResult::ImageClicked(image_id) => Some(image_id),
+            EventResult::ContextMenu(ref point_info) => point_info.image_id,
+            _ => None,
+        }
+    }
+
+    // Returns `self`, or `other` if nothing happened.
+    fn or(self, other: EventResult) -> EventResult {
+        if self.is_none() {
+            other
+        } else {
+            self
+        }
+    }
 }
 
 #[derive(Clone, Copy, Debug, PartialEq)]
@@ -834,12 +1770,34 @@ pub enum MouseEventKind {
     Left = 0,
     LeftDouble,
     LeftTriple,
+    /// A secondary click, which asks for a context menu.
+    Right,
+}
+
+/// A platform-neutral navigation key. Hosts map their own key bindings onto these.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[repr(C)]
+pub enum Key {
+    Left = 0,
+    Right,
+    Up,
+    Down,
+    WordLeft,
+    WordRight,
+    LineStart,
+    LineEnd,
+    DocumentStart,
+    DocumentEnd,
+    PageUp,
+    PageDown,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 struct Selection {
     range: Range<TextLocation>,
     direction: SelectionDirection,
+    /// Which line the focus belongs to when it falls on a soft line break.
+    affinity: CaretAffinity,
 }
 
 impl Selection {
@@ -848,6 +1806,48 @@ impl Selection {
         Selection {
             range,
             direction: SelectionDirection::Forward,
+            affinity: CaretAffinity::Downstream,
+        }
+    }
+
+    #[inline]
+    fn caret(location: TextLocation) -> Selection {
+        Selection::forward(location..location)
+    }
+
+    #[inline]
+    fn is_collapsed(&self) -> bool {
+        self.range.start == self.range.end
+    }
+
+    /// The end that stays put when the selection is extended.
+    #[inline]
+    fn anchor(&self) -> TextLocation {
+        match self.direction {
+            SelectionDirection::Forward => self.range.start,
+            SelectionDirection::Backward => self.range.end,
+        }
+    }
+
+    /// The end that moves when the selection is extended.
+    #[inline]
+    fn focus(&self) -> TextLocation {
+        match self.direction {
+            SelectionDirection::Forward => self.range.end,
+            SelectionDirection::Backward => self.range.start,
+        }
+    }
+
+    fn extended_to(&self, focus: TextLocation) -> Selection {
+        let anchor = self.anchor();
+        if focus < anchor {
+            Selection {
+                range: focus..anchor,
+                direction: SelectionDirection::Backward,
+                affinity: CaretAffinity::Downstream,
+            }
+        } else {
+            Selection::forward(anchor..focus)
         }
     }
 }
@@ -858,6 +1858,16 @@ enum SelectionDirection {
     Forward,
 }
 
+/// A location at a soft line break is both the end of one line and the start of the next.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum CaretAffinity {
+    /// The location belongs to the start of the next line.
+    Downstream,
+    /// The location belongs to the end of the line before, as after moving to the end of a line
+    /// that wraps without whitespace.
+    Upstream,
+}
+
 #[derive(Clone, Copy, Debug, PartialEq)]
 enum MouseStatus {
     Up,
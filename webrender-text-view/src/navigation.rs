@@ -0,0 +1,220 @@
+// WRTextView/webrender-text-view/src/navigation.rs
+//
+// Copyright © 2018 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use euclid::Point2D;
+use pilcrow::TextLocation;
+use std::cmp;
+use {CaretAffinity, EventResult, Key, Modifiers, SectionExt, Selection, View};
+
+impl View {
+    /// Moves the caret, or extends the selection if Shift is held. Any input method composition
+    /// is committed first.
+    pub fn key_down(&mut self, key: Key, modifiers: Modifiers) -> EventResult {
+        let selection_before = self.selection.clone();
+        let selection = match self.selection {
+            None => Selection::caret(TextLocation::beginning()),
+            Some(ref selection) => selection.clone(),
+        };
+        let extend = modifiers.contains(Modifiers::SHIFT);
+        self.marked_range = None;
+        self.undo_manager.break_coalescing();
+
+        match key {
+            Key::Up | Key::Down | Key::PageUp | Key::PageDown => {}
+            _ => self.goal_inline_position = None,
+        }
+
+        let (focus, affinity) = (selection.focus(), selection.affinity);
+        let downstream = |location| (location, CaretAffinity::Downstream);
+        let (new_focus, new_affinity) = match key {
+            // Without Shift, Left and Right collapse a selection to the edge in that direction.
+            Key::Left if !extend && !selection.is_collapsed() => downstream(selection.range.start),
+            Key::Right if !extend && !selection.is_collapsed() => downstream(selection.range.end),
+            Key::Left => downstream(self.previous_location(&focus).unwrap_or(focus)),
+            Key::Right => downstream(self.next_location(&focus).unwrap_or(focus)),
+            Key::WordLeft => downstream(self.previous_word_boundary(&focus)),
+            Key::WordRight => downstream(self.next_word_boundary(&focus)),
+            Key::LineStart => downstream(self.line_boundary(&focus, affinity, false)),
+            // Stay on this line even if it wraps without whitespace, where its end is also the
+            // start of the next line.
+            Key::LineEnd => (self.line_boundary(&focus, affinity, true), CaretAffinity::Upstream),
+            Key::DocumentStart => downstream(TextLocation::beginning()),
+            Key::DocumentEnd => downstream(self.end_location()),
+            Key::Up => self.vertically_moved_location(&focus, affinity, true, 0.0),
+            Key::Down => self.vertically_moved_location(&focus, affinity, false, 0.0),
+            Key::PageUp => {
+                let page_height = self.visible_height();
+                self.vertically_moved_location(&focus, affinity, true, page_height)
+            }
+            Key::PageDown => {
+                let page_height = self.visible_height();
+                self.vertically_moved_location(&focus, affinity, false, page_height)
+            }
+        };
+
+        let mut new_selection = if extend {
+            selection.extended_to(new_focus)
+        } else {
+            Selection::caret(new_focus)
+        };
+        new_selection.affinity = new_affinity;
+        self.selection = Some(new_selection);
+        self.caret_visible = true;
+        self.rebuild_display_list();
+        self.selection_changed_event(&selection_before)
+    }
+
+    fn previous_word_boundary(&self, location: &TextLocation) -> TextLocation {
+        if location.character_index == 0 {
+            return self.previous_location(location).unwrap_or(*location)
+        }
+
+        let chars = self.paragraph_chars(location.paragraph_index);
+        let mut index = cmp::min(location.character_index, chars.len());
+        while index > 0 && !is_word_char(chars[index - 1]) {
+            index -= 1
+        }
+        while index > 0 && is_word_char(chars[index - 1]) {
+            index -= 1
+        }
+        TextLocation::new(location.paragraph_index, index)
+    }
+
+    fn next_word_boundary(&self, location: &TextLocation) -> TextLocation {
+        let chars = self.paragraph_chars(location.paragraph_index);
+        let mut index = location.character_index;
+        if index >= chars.len() {
+            return self.next_location(location).unwrap_or(*location)
+        }
+
+        while index < chars.len() && !is_word_char(chars[index]) {
+            index += 1
+        }
+        while index < chars.len() && is_word_char(chars[index]) {
+            index += 1
+        }
+        TextLocation::new(location.paragraph_index, index)
+    }
+
+    fn line_boundary(&self, location: &TextLocation, affinity: CaretAffinity, end: bool)
+                     -> TextLocation {
+        let line_index = match self.section.line_index_for_location(location, affinity) {
+            None => return *location,
+            Some(line_index) => line_index,
+        };
+        let lines = self.section.frames()[location.paragraph_index].lines();
+        let character_index = if end {
+            self.line_end_char_index(location.paragraph_index, line_index)
+        } else {
+            lines[line_index].char_range().start
+        };
+        TextLocation::new(location.paragraph_index, character_index)
+    }
+
+    /// Moves up or down by one line, or by at least `distance` if it's nonzero, keeping the
+    /// caret as close as possible to the inline position it started at.
+    fn vertically_moved_location(&mut self,
+                                 location: &TextLocation,
+                                 affinity: CaretAffinity,
+                                 up: bool,
+                                 distance: f32)
+                                 -> (TextLocation, CaretAffinity) {
+        let line_index = match self.section.line_index_for_location(location, affinity) {
+            None => return (*location, affinity),
+            Some(line_index) => line_index,
+        };
+        let all_lines: Vec<(usize, usize)> =
+            self.section.frames().iter().enumerate().flat_map(|(frame_index, frame)| {
+                (0..frame.lines().len()).map(move |line_index| (frame_index, line_index))
+            }).collect();
+        let mut index = match all_lines.iter().position(|&position| {
+            position == (location.paragraph_index, line_index)
+        }) {
+            None => return (*location, affinity),
+            Some(index) => index,
+        };
+
+        let goal_inline_position = match self.goal_inline_position {
+            Some(goal_inline_position) => goal_inline_position,
+            None => {
+                match self.section.caret_bounds(location, affinity, 0.0) {
+                    None => return (*location, affinity),
+                    Some(caret_bounds) => caret_bounds.origin.x,
+                }
+            }
+        };
+        self.goal_inline_position = Some(goal_inline_position);
+
+        let start_y = self.line_origin_y(all_lines[index]);
+        loop {
+            // Moving past the first or last line goes to the start or end of the document.
+            index = match (up, index) {
+                (true, 0) => return (TextLocation::beginning(), CaretAffinity::Downstream),
+                (true, index) => index - 1,
+                (false, index) if index + 1 == all_lines.len() => {
+                    return (self.end_location(), CaretAffinity::Downstream)
+                }
+                (false, index) => index + 1,
+            };
+            if (self.line_origin_y(all_lines[index]) - start_y).abs() >= distance {
+                break
+            }
+        }
+
+        let (frame_index, line_index) = all_lines[index];
+        let lines = self.section.frames()[frame_index].lines();
+        let line = &lines[line_index];
+        let point = Point2D::new(goal_inline_position, line.origin.y);
+        let (character_index, affinity) = match line.char_index_for_position(&point) {
+            Some(character_index) => (character_index, CaretAffinity::Downstream),
+            None if goal_inline_position <= line.origin.x => {
+                (line.char_range().start, CaretAffinity::Downstream)
+            }
+            None => (self.line_end_char_index(frame_index, line_index), CaretAffinity::Upstream),
+        };
+        (TextLocation::new(frame_index, character_index), affinity)
+    }
+
+    fn line_origin_y(&self, (frame_index, line_index): (usize, usize)) -> f32 {
+        self.section.frames()[frame_index].lines()[line_index].origin.y
+    }
+
+    // A soft line break after a space belongs to the next line, so stop just before the space.
+    // Lines that break without whitespace, as CJK text and overlong words do, end at their end.
+    fn line_end_char_index(&self, paragraph_index: usize, line_index: usize) -> usize {
+        let lines = self.section.frames()[paragraph_index].lines();
+        let char_range = lines[line_index].char_range();
+        if line_index + 1 == lines.len() || char_range.end == char_range.start {
+            return char_range.end
+        }
+        let last_char_range = TextLocation::new(paragraph_index, char_range.end - 1)..
+            TextLocation::new(paragraph_index, char_range.end);
+        let last_char = self.document.copy_string_in_range(last_char_range);
+        if last_char.chars().all(char::is_whitespace) {
+            char_range.end - 1
+        } else {
+            char_range.end
+        }
+    }
+
+    fn paragraph_chars(&self, paragraph_index: usize) -> Vec<char> {
+        let char_len = match self.document.paragraphs().get(paragraph_index) {
+            None => return vec![],
+            Some(paragraph) => paragraph.char_len(),
+        };
+        let range = TextLocation::new(paragraph_index, 0)..
+            TextLocation::new(paragraph_index, char_len);
+        self.document.copy_string_in_range(range).chars().collect()
+    }
+}
+
+fn is_word_char(ch: char) -> bool {
+    ch.is_alphanumeric() || ch == '_'
+}
@@ -0,0 +1,139 @@
+// WRTextView/webrender-text-view/src/resource_api.rs
+//
+// Copyright © 2018 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::cell::{Cell, Ref, RefCell};
+use std::rc::Rc;
+use webrender_api::{DocumentId, FontInstanceKey, FontKey, IdNamespace, RenderApi};
+use webrender_api::{ResourceUpdates, Transaction};
+
+/// The part of the WebRender API that layout and scene building use: key generation and
+/// resource updates.
+pub trait ResourceApi {
+    fn generate_font_key(&self) -> FontKey;
+    fn generate_font_instance_key(&self) -> FontInstanceKey;
+    fn send_transaction(&self, transaction: Transaction);
+
+    fn update_resources(&self, resource_updates: ResourceUpdates) {
+        let mut transaction = Transaction::new();
+        transaction.update_resources(resource_updates);
+        self.send_transaction(transaction)
+    }
+}
+
+impl<T> ResourceApi for Rc<T> where T: ResourceApi {
+    #[inline]
+    fn generate_font_key(&self) -> FontKey {
+        (**self).generate_font_key()
+    }
+
+    #[inline]
+    fn generate_font_instance_key(&self) -> FontInstanceKey {
+        (**self).generate_font_instance_key()
+    }
+
+    #[inline]
+    fn send_transaction(&self, transaction: Transaction) {
+        (**self).send_transaction(transaction)
+    }
+
+    #[inline]
+    fn update_resources(&self, resource_updates: ResourceUpdates) {
+        (**self).update_resources(resource_updates)
+    }
+}
+
+/// Sends everything to a WebRender document.
+pub struct WrResourceApi {
+    api: RenderApi,
+    document_id: DocumentId,
+}
+
+impl WrResourceApi {
+    #[inline]
+    pub fn new(api: RenderApi, document_id: DocumentId) -> WrResourceApi {
+        WrResourceApi {
+            api,
+            document_id,
+        }
+    }
+}
+
+impl ResourceApi for WrResourceApi {
+    #[inline]
+    fn generate_font_key(&self) -> FontKey {
+        self.api.generate_font_key()
+    }
+
+    #[inline]
+    fn generate_font_instance_key(&self) -> FontInstanceKey {
+        self.api.generate_font_instance_key()
+    }
+
+    #[inline]
+    fn send_transaction(&self, transaction: Transaction) {
+        self.api.send_transaction(self.document_id, transaction)
+    }
+}
+
+/// An in-memory stand-in for WebRender, for tests.
+///
+/// Keys are handed out sequentially, and resource updates are kept so they can be inspected.
+pub struct FakeResourceApi {
+    next_key: Cell<u32>,
+    resource_updates: RefCell<Vec<ResourceUpdates>>,
+    transaction_count: Cell<usize>,
+}
+
+impl FakeResourceApi {
+    pub fn new() -> FakeResourceApi {
+        FakeResourceApi {
+            next_key: Cell::new(0),
+            resource_updates: RefCell::new(vec![]),
+            transaction_count: Cell::new(0),
+        }
+    }
+
+    /// Returns every batch of resource updates sent so far, in order.
+    #[inline]
+    pub fn resource_updates(&self) -> Ref<Vec<ResourceUpdates>> {
+        self.resource_updates.borrow()
+    }
+
+    /// Returns the number of transactions sent so far, including resource updates.
+    #[inline]
+    pub fn transaction_count(&self) -> usize {
+        self.transaction_count.get()
+    }
+
+    fn generate_key(&self) -> u32 {
+        let key = self.next_key.get();
+        self.next_key.set(key + 1);
+        key
+    }
+}
+
+impl ResourceApi for FakeResourceApi {
+    fn generate_font_key(&self) -> FontKey {
+        FontKey::new(IdNamespace(0), self.generate_key())
+    }
+
+    fn generate_font_instance_key(&self) -> FontInstanceKey {
+        FontInstanceKey::new(IdNamespace(0), self.generate_key())
+    }
+
+    fn send_transaction(&self, _: Transaction) {
+        self.transaction_count.set(self.transaction_count.get() + 1)
+    }
+
+    fn update_resources(&self, resource_updates: ResourceUpdates) {
+        self.resource_updates.borrow_mut().push(resource_updates);
+        self.transaction_count.set(self.transaction_count.get() + 1)
+    }
+}
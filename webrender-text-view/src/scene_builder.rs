@@ -8,37 +8,63 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
-use pilcrow::{Frame, Line, ParagraphContent, ParagraphStyle, Run, Section};
+use pilcrow::{Decoration, Frame, Line, ParagraphContent, ParagraphStyle, Run, Section};
 use std::cmp;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::ops::Range;
-use webrender_api::{AlphaType, ColorF, DisplayListBuilder, FontRenderMode, GlyphInstance, GlyphOptions};
+use webrender_api::{AlphaType, BorderRadius, ClipMode, ColorF, ComplexClipRegion};
+use webrender_api::{DisplayListBuilder, FontRenderMode, GlyphInstance, GlyphOptions};
 use webrender_api::{GlyphRasterSpace, IdNamespace, ImageKey, ImageRendering, LayoutPrimitiveInfo, LayoutPoint};
-use webrender_api::{LayoutRect, LayoutSize, LineOrientation, LineStyle, MixBlendMode, RenderApi};
+use webrender_api::{LayoutRect, LayoutSize, LineOrientation, LineStyle, LocalClip, MixBlendMode};
 use webrender_api::{ResourceUpdates, ScrollPolicy, TransformStyle};
-use {ComputedStyle, FontInstanceInfo, FontKeyMap, ImageMap, LinkId, PIPELINE_ID};
-use {TextLocation, WrDisplayList};
+use {CaretAffinity, ColorExt, ComputedDecoration, ComputedStyle, FontBackend, FontInstanceInfo};
+use {FontKeyMap, Highlight, HighlightStyle, ImageMap, LinkId, PIPELINE_ID, ResourceApi};
+use {SectionExt, SelectionHandle, TextLocation, Theme, WrDisplayList};
 
-const BLACK_COLOR: ColorF = ColorF {
-    r: 0.0,
-    g: 0.0,
-    b: 0.0,
-    a: 1.0,
-};
+const HIGHLIGHT_LINE_THICKNESS: f32 = 1.0;
+const WAVY_LINE_HEIGHT_FACTOR: f32 = 3.0;
+const MARKED_TEXT_UNDERLINE_THICKNESS: f32 = 1.0;
 
-pub struct SceneBuilder {
+pub struct SceneBuilder<'a> {
     display_list_builder: DisplayListBuilder,
-    selection: Option<Range<TextLocation>>,
-    active_link_id: Option<LinkId>,
-    selection_background_color: ColorF,
+    state: SceneState<'a>,
 }
 
-impl SceneBuilder {
-    pub fn new(layout_size: &LayoutSize,
-               selection: &Option<Range<TextLocation>>,
-               active_link_id: &Option<LinkId>,
-               selection_background_color: &ColorF)
-               -> SceneBuilder {
+/// Find results to highlight.
+#[derive(Clone, Debug)]
+pub struct FindHighlights {
+    pub matches: Vec<Range<TextLocation>>,
+    pub current_match: Option<usize>,
+}
+
+/// The view state, besides its layout, that determines what gets drawn. It is borrowed from the
+/// view rather than copied, since the display list is rebuilt on every hover and drag.
+#[derive(Clone, Copy)]
+pub struct SceneState<'a> {
+    pub selection: &'a Option<Range<TextLocation>>,
+    pub active_link_id: Option<LinkId>,
+    pub hovered_link_id: Option<LinkId>,
+    pub visited_link_ids: &'a HashSet<LinkId>,
+    pub theme: &'a Theme,
+    pub focused: bool,
+    pub caret: Option<Caret>,
+    /// Whether to draw touch handles at the ends of the selection.
+    pub selection_handles: bool,
+    pub marked_range: &'a Option<Range<TextLocation>>,
+    pub find_highlights: &'a Option<FindHighlights>,
+    pub highlights: &'a [Highlight],
+}
+
+/// How to draw the caret when the selection is collapsed.
+#[derive(Clone, Copy, Debug)]
+pub struct Caret {
+    pub color: ColorF,
+    pub width: f32,
+    pub(crate) affinity: CaretAffinity,
+}
+
+impl<'a> SceneBuilder<'a> {
+    pub fn new(layout_size: &LayoutSize, state: SceneState<'a>) -> SceneBuilder<'a> {
         let mut display_list_builder = DisplayListBuilder::new(PIPELINE_ID, *layout_size);
         let root_stacking_context_bounds = LayoutRect::new(LayoutPoint::zero(), *layout_size);
         let root_layout_primitive_info = LayoutPrimitiveInfo::new(root_stacking_context_bounds);
@@ -51,11 +77,15 @@ impl SceneBuilder {
                                                    MixBlendMode::Normal,
                                                    vec![],
                                                    GlyphRasterSpace::Screen);
+
+        if state.theme.page_background_color.a_f32() > 0.0 {
+            display_list_builder.push_rect(&root_layout_primitive_info,
+                                           state.theme.page_background_color.to_colorf());
+        }
+
         SceneBuilder {
             display_list_builder,
-            selection: (*selection).clone(),
-            active_link_id: *active_link_id,
-            selection_background_color: *selection_background_color,
+            state,
         }
     }
 
@@ -65,16 +95,36 @@ impl SceneBuilder {
     }
 
     pub(crate) fn build_display_list(&mut self,
-                                     render_api: &RenderApi,
+                                     render_api: &ResourceApi,
                                      resource_updates: &mut ResourceUpdates,
                                      font_keys: &mut FontKeyMap,
+                                     font_backend: &FontBackend,
                                      images: &mut ImageMap,
                                      section: &Section) {
         for (frame_index, frame) in section.frames().iter().enumerate() {
             let frame_char_len = frame.char_len();
 
             for line in frame.lines() {
-                if self.selection.is_some() {
+                // Compute styles up front, since run backgrounds go beneath the selection.
+                let runs = line.runs();
+                let computed_styles: Vec<ComputedStyle> = runs.iter().map(|run| {
+                    ComputedStyle::from_formatting(run.formatting(),
+                                                   &self.state.active_link_id,
+                                                   &self.state.hovered_link_id,
+                                                   self.state.visited_link_ids,
+                                                   self.state.theme,
+                                                   font_keys,
+                                                   font_backend,
+                                                   render_api,
+                                                   resource_updates)
+                }).collect();
+
+                self.add_run_backgrounds_for_line(&line, &runs, &computed_styles);
+                self.add_highlights_for_line(frame_index, frame_char_len, &line);
+                if self.state.find_highlights.is_some() {
+                    self.add_find_highlights_for_line(frame_index, frame_char_len, &line)
+                }
+                if self.state.selection.is_some() {
                     self.add_selection_background_for_line_if_necessary(frame_index,
                                                                         frame_char_len,
                                                                         &line)
@@ -90,7 +140,7 @@ impl SceneBuilder {
                                     line_size);
                 let line_layout_primitive_info = LayoutPrimitiveInfo::new(line_bounds);
 
-                for run in line.runs() {
+                for (run, computed_style) in runs.iter().zip(computed_styles.into_iter()) {
                     let mut glyphs = vec![];
                     for (index, position) in run.glyphs()
                                                 .into_iter()
@@ -101,12 +151,6 @@ impl SceneBuilder {
                         })
                     }
 
-                    let computed_style = ComputedStyle::from_formatting(run.formatting(),
-                                                                        &self.active_link_id,
-                                                                        font_keys,
-                                                                        &render_api,
-                                                                        resource_updates);
-
                     if let Some(image_id) = computed_style.image {
                         if let Some(image_info) = images.read().unwrap().get(&image_id) {
                             // TODO(pcwalton): Take device pixel ratio into account!
@@ -131,79 +175,343 @@ impl SceneBuilder {
                                                           .get(&computed_font_id)
                                                           .unwrap();
 
-                        let text_color = computed_style.color.unwrap_or(BLACK_COLOR);
+                        let default_text_color = self.state.theme.text_color.to_colorf();
+                        let text_color = computed_style.color.unwrap_or(default_text_color);
                         let glyph_options = GlyphOptions {
                             render_mode: FontRenderMode::Subpixel,
                             ..GlyphOptions::default()
                         };
+
+                        // The shadow applies to the text and its decorations. Its clip is
+                        // widened by the shadow's offset and blur radius so that the blurred
+                        // copy isn't cut off at the edges of the line.
+                        if let Some(ref shadow) = computed_style.shadow {
+                            let shadow_clip_rect =
+                                line_bounds.inflate(shadow.offset.x.abs() + shadow.blur_radius,
+                                                    shadow.offset.y.abs() + shadow.blur_radius);
+                            let shadow_layout_primitive_info =
+                                LayoutPrimitiveInfo::with_clip_rect(line_bounds, shadow_clip_rect);
+                            self.display_list_builder.push_shadow(&shadow_layout_primitive_info,
+                                                                  shadow.clone());
+                        }
+
                         self.display_list_builder.push_text(&line_layout_primitive_info,
                                                             &glyphs,
                                                             font_instance_info.key.clone(),
                                                             text_color,
                                                             Some(glyph_options));
 
-                        if computed_style.underline {
+                        if !computed_style.decorations.is_empty() {
                             let origin = match glyphs.get(0) {
                                 None => line_origin,
                                 Some(ref glyph) => glyph.point,
                             };
-                            self.add_underline(&font_instance_info, &run, &origin, &text_color);
+                            self.add_decorations(&font_instance_info,
+                                                 &run,
+                                                 &origin,
+                                                 &text_color,
+                                                 &computed_style.decorations);
+                        }
+
+                        if computed_style.shadow.is_some() {
+                            self.display_list_builder.pop_all_shadows();
                         }
                     }
                 }
+
+                if self.state.marked_range.is_some() {
+                    self.add_marked_text_underline_for_line_if_necessary(frame_index,
+                                                                         frame_char_len,
+                                                                         &line)
+                }
             }
 
             self.add_frame_decorations(&frame)
         }
+
+        self.add_caret_if_necessary(section);
+        self.add_selection_handles_if_necessary(section)
     }
 
     fn add_selection_background_for_line_if_necessary(&mut self,
                                                       frame_index: usize,
                                                       frame_char_len: usize,
                                                       line: &Line) {
-        let typo_bounds = line.typographic_bounds();
-        let line_origin = LayoutPoint::from_untyped(&line.origin);
-        let line_size = LayoutSize::new(typo_bounds.width,
-                                        typo_bounds.ascent + typo_bounds.descent);
-        let line_bounds = LayoutRect::new(LayoutPoint::new(line_origin.x,
-                                                        line_origin.y - typo_bounds.ascent),
-                                          line_size);
-
-        let selection = self.selection.clone().unwrap();
+        let selection = self.state.selection.clone().unwrap();
         let selected_char_range = match selection.char_range_for_paragraph(frame_index,
                                                                            frame_char_len) {
             None => return,
             Some(char_range) => char_range.intersect(&line.char_range()),
         };
 
-        let start_offset = line.inline_position_for_char_index(selected_char_range.start);
-        let end_offset = line.inline_position_for_char_index(selected_char_range.end);
-        let selection_bounds =
-            LayoutRect::new(LayoutPoint::new(line_bounds.origin.x + start_offset,
-                                             line_bounds.origin.y),
-                            LayoutSize::new(end_offset - start_offset, line_size.height));
+        let selection_bounds = line.bounds_for_char_range(&selected_char_range);
         let layout_primitive_info = LayoutPrimitiveInfo::new(selection_bounds);
+        let selection_background_color = if self.state.focused {
+            self.state.theme.selection_background_color
+        } else {
+            self.state.theme.inactive_selection_background_color
+        };
         self.display_list_builder.push_rect(&layout_primitive_info,
-                                            self.selection_background_color)
+                                            selection_background_color.to_colorf())
     }
 
-    fn add_underline(&mut self,
-                     font_instance_info: &FontInstanceInfo,
-                     run: &Run,
-                     run_origin: &LayoutPoint,
-                     color: &ColorF) {
+    fn add_run_backgrounds_for_line(&mut self,
+                                    line: &Line,
+                                    runs: &[Run],
+                                    computed_styles: &[ComputedStyle]) {
+        let line_typo_bounds = line.typographic_bounds();
+        for (run, computed_style) in runs.iter().zip(computed_styles.iter()) {
+            let background_color = match computed_style.background_color {
+                None => continue,
+                Some(background_color) => background_color,
+            };
+            let run_offset = line.inline_position_for_char_index(run.char_range().start);
+            let origin = LayoutPoint::new(line.origin.x + run_offset,
+                                          line.origin.y - line_typo_bounds.ascent);
+            let size = LayoutSize::new(run.typographic_bounds().width,
+                                       line_typo_bounds.ascent + line_typo_bounds.descent);
+            let layout_primitive_info = LayoutPrimitiveInfo::new(LayoutRect::new(origin, size));
+            self.display_list_builder.push_rect(&layout_primitive_info, background_color)
+        }
+    }
+
+    // Highlights are already sorted by z-index.
+    fn add_highlights_for_line(&mut self,
+                               frame_index: usize,
+                               frame_char_len: usize,
+                               line: &Line) {
+        let highlights = self.state.highlights;
+        for highlight in highlights {
+            let char_range = match highlight.range.char_range_for_paragraph(frame_index,
+                                                                            frame_char_len) {
+                None => continue,
+                Some(char_range) => char_range.intersect(&line.char_range()),
+            };
+            if char_range.start >= char_range.end {
+                continue
+            }
+
+            let bounds = line.bounds_for_char_range(&char_range);
+            let color = highlight.color.to_colorf();
+            match highlight.style {
+                HighlightStyle::Background => {
+                    let layout_primitive_info = LayoutPrimitiveInfo::new(bounds);
+                    self.display_list_builder.push_rect(&layout_primitive_info, color)
+                }
+                HighlightStyle::Underline => {
+                    self.add_highlight_underline(&bounds, &color, LineStyle::Solid)
+                }
+                HighlightStyle::WavyUnderline => {
+                    self.add_highlight_underline(&bounds, &color, LineStyle::Wavy)
+                }
+                HighlightStyle::DottedUnderline => {
+                    self.add_highlight_underline(&bounds, &color, LineStyle::Dotted)
+                }
+                HighlightStyle::DashedUnderline => {
+                    self.add_highlight_underline(&bounds, &color, LineStyle::Dashed)
+                }
+                HighlightStyle::Outline => self.add_outline(&bounds, &color),
+            }
+        }
+    }
+
+    // Wavy lines oscillate within their bounds, so they get more room than the other styles.
+    fn add_highlight_underline(&mut self, bounds: &LayoutRect, color: &ColorF, style: LineStyle) {
+        let thickness = HIGHLIGHT_LINE_THICKNESS;
+        let height = match style {
+            LineStyle::Wavy => thickness * WAVY_LINE_HEIGHT_FACTOR,
+            _ => thickness,
+        };
+        let origin = LayoutPoint::new(bounds.origin.x, bounds.max_y() - height);
+        let size = LayoutSize::new(bounds.size.width, height);
+        let layout_primitive_info = LayoutPrimitiveInfo::new(LayoutRect::new(origin, size));
+        self.display_list_builder.push_line(&layout_primitive_info,
+                                            thickness,
+                                            LineOrientation::Horizontal,
+                                            color,
+                                            style)
+    }
+
+    fn add_outline(&mut self, bounds: &LayoutRect, color: &ColorF) {
+        let thickness = HIGHLIGHT_LINE_THICKNESS;
+        let edges = [
+            LayoutRect::new(bounds.origin, LayoutSize::new(bounds.size.width, thickness)),
+            LayoutRect::new(LayoutPoint::new(bounds.origin.x, bounds.max_y() - thickness),
+                            LayoutSize::new(bounds.size.width, thickness)),
+            LayoutRect::new(bounds.origin, LayoutSize::new(thickness, bounds.size.height)),
+            LayoutRect::new(LayoutPoint::new(bounds.max_x() - thickness, bounds.origin.y),
+                            LayoutSize::new(thickness, bounds.size.height)),
+        ];
+        for edge in &edges {
+            self.display_list_builder.push_rect(&LayoutPrimitiveInfo::new(*edge), *color)
+        }
+    }
+
+    fn add_find_highlights_for_line(&mut self,
+                                    frame_index: usize,
+                                    frame_char_len: usize,
+                                    line: &Line) {
+        let find_highlights = match *self.state.find_highlights {
+            None => return,
+            Some(ref find_highlights) => find_highlights,
+        };
+        for (match_index, find_match) in find_highlights.matches.iter().enumerate() {
+            let match_char_range = match find_match.char_range_for_paragraph(frame_index,
+                                                                             frame_char_len) {
+                None => continue,
+                Some(char_range) => char_range.intersect(&line.char_range()),
+            };
+            if match_char_range.start >= match_char_range.end {
+                continue
+            }
+
+            let color = if find_highlights.current_match == Some(match_index) {
+                self.state.theme.current_find_match_color.to_colorf()
+            } else {
+                self.state.theme.find_match_color.to_colorf()
+            };
+            let layout_primitive_info =
+                LayoutPrimitiveInfo::new(line.bounds_for_char_range(&match_char_range));
+            self.display_list_builder.push_rect(&layout_primitive_info, color)
+        }
+    }
+
+    // Input method compositions get a dotted underline, to set them apart from committed text.
+    fn add_marked_text_underline_for_line_if_necessary(&mut self,
+                                                       frame_index: usize,
+                                                       frame_char_len: usize,
+                                                       line: &Line) {
+        let marked_range = self.state.marked_range.clone().unwrap();
+        let marked_char_range = match marked_range.char_range_for_paragraph(frame_index,
+                                                                            frame_char_len) {
+            None => return,
+            Some(char_range) => char_range.intersect(&line.char_range()),
+        };
+        if marked_char_range.start >= marked_char_range.end {
+            return
+        }
+
+        let marked_bounds = line.bounds_for_char_range(&marked_char_range);
+        let typo_bounds = line.typographic_bounds();
+        let thickness = MARKED_TEXT_UNDERLINE_THICKNESS;
+        let origin = LayoutPoint::new(marked_bounds.origin.x,
+                                      line.origin.y + typo_bounds.descent * 0.5);
+        let size = LayoutSize::new(marked_bounds.size.width, thickness);
+        let layout_primitive_info = LayoutPrimitiveInfo::new(LayoutRect::new(origin, size));
+        let color = self.state.theme.marked_text_underline_color.to_colorf();
+        self.display_list_builder.push_line(&layout_primitive_info,
+                                            thickness,
+                                            LineOrientation::Horizontal,
+                                            &color,
+                                            LineStyle::Dotted)
+    }
+
+    fn add_caret_if_necessary(&mut self, section: &Section) {
+        let caret = match self.state.caret {
+            None => return,
+            Some(caret) => caret,
+        };
+        let location = match *self.state.selection {
+            Some(ref selection) if selection.start == selection.end => selection.start,
+            _ => return,
+        };
+        if let Some(caret_bounds) = section.caret_bounds(&location, caret.affinity, caret.width) {
+            let layout_primitive_info = LayoutPrimitiveInfo::new(caret_bounds);
+            self.display_list_builder.push_rect(&layout_primitive_info, caret.color)
+        }
+    }
+
+    fn add_selection_handles_if_necessary(&mut self, section: &Section) {
+        if !self.state.selection_handles || !self.state.focused {
+            return
+        }
+        let selection = match *self.state.selection {
+            Some(ref selection) if selection.start != selection.end => selection.clone(),
+            _ => return,
+        };
+
+        let color = self.state.theme.selection_handle_color.to_colorf();
+        for &(ref location, handle) in &[(selection.start, SelectionHandle::Start),
+                                         (selection.end, SelectionHandle::End)] {
+            let bounds = match section.selection_handle_bounds(location, handle) {
+                None => continue,
+                Some(bounds) => bounds,
+            };
+            self.display_list_builder.push_rect(&LayoutPrimitiveInfo::new(bounds.stem), color);
+
+            let knob_radius = BorderRadius::uniform(bounds.knob.size.width * 0.5);
+            let knob_clip = ComplexClipRegion::new(bounds.knob, knob_radius, ClipMode::Clip);
+            let knob_primitive_info =
+                LayoutPrimitiveInfo::with_clip(bounds.knob,
+                                               LocalClip::RoundedRect(bounds.knob, knob_clip));
+            self.display_list_builder.push_rect(&knob_primitive_info, color)
+        }
+    }
+
+    fn add_decorations(&mut self,
+                       font_instance_info: &FontInstanceInfo,
+                       run: &Run,
+                       run_origin: &LayoutPoint,
+                       text_color: &ColorF,
+                       decorations: &[ComputedDecoration]) {
         let typographic_bounds = run.typographic_bounds();
-        let origin = LayoutPoint::new(run_origin.x,
-                                      run_origin.y - font_instance_info.underline_position);
-        let size = LayoutSize::new(typographic_bounds.width,
-                                   font_instance_info.underline_thickness);
+        let underline_position = font_instance_info.underline_position;
+        let underline_thickness = font_instance_info.underline_thickness;
+        for decoration in decorations {
+            let color = decoration.color.unwrap_or(*text_color);
+            match decoration.kind {
+                Decoration::Underline => {
+                    self.add_decoration_line(run_origin,
+                                             typographic_bounds.width,
+                                             underline_position,
+                                             underline_thickness,
+                                             &color)
+                }
+                Decoration::DoubleUnderline => {
+                    self.add_decoration_line(run_origin,
+                                             typographic_bounds.width,
+                                             underline_position,
+                                             underline_thickness,
+                                             &color);
+                    self.add_decoration_line(run_origin,
+                                             typographic_bounds.width,
+                                             underline_position - underline_thickness * 2.0,
+                                             underline_thickness,
+                                             &color)
+                }
+                Decoration::Overline => {
+                    self.add_decoration_line(run_origin,
+                                             typographic_bounds.width,
+                                             typographic_bounds.ascent,
+                                             underline_thickness,
+                                             &color)
+                }
+                Decoration::Strikethrough => {
+                    self.add_decoration_line(run_origin,
+                                             typographic_bounds.width,
+                                             font_instance_info.strikeout_position,
+                                             font_instance_info.strikeout_thickness,
+                                             &color)
+                }
+            }
+        }
+    }
+
+    // Like font metrics, `position` is the top of the line, measured upward from the baseline.
+    fn add_decoration_line(&mut self,
+                           run_origin: &LayoutPoint,
+                           width: f32,
+                           position: f32,
+                           thickness: f32,
+                           color: &ColorF) {
+        let origin = LayoutPoint::new(run_origin.x, run_origin.y - position);
+        let size = LayoutSize::new(width, thickness);
         let layout_primitive_info = LayoutPrimitiveInfo::new(LayoutRect::new(origin, size));
         self.display_list_builder.push_line(&layout_primitive_info,
-                                            size.height,
+                                            thickness,
                                             LineOrientation::Horizontal,
                                             color,
-                                            LineStyle::Solid);
-        eprintln!("added underline: origin={:?} size={:?}", origin, size);
+                                            LineStyle::Solid)
     }
 
     fn add_frame_decorations(&mut self, frame: &Frame) {
@@ -218,13 +526,30 @@ impl SceneBuilder {
                 self.display_list_builder.push_line(&layout_primitive_info,
                                                     1.0,
                                                     LineOrientation::Horizontal,
-                                                    &BLACK_COLOR,
+                                                    &self.state.theme.rule_color.to_colorf(),
                                                     LineStyle::Solid)
             }
         }
     }
 }
 
+trait LineExt {
+    fn bounds_for_char_range(&self, char_range: &Range<usize>) -> LayoutRect;
+}
+
+impl LineExt for Line {
+    fn bounds_for_char_range(&self, char_range: &Range<usize>) -> LayoutRect {
+        let typo_bounds = self.typographic_bounds();
+        let start_offset = self.inline_position_for_char_index(char_range.start);
+        let end_offset = self.inline_position_for_char_index(char_range.end);
+        let origin = LayoutPoint::new(self.origin.x + start_offset,
+                                      self.origin.y - typo_bounds.ascent);
+        let size = LayoutSize::new(end_offset - start_offset,
+                                   typo_bounds.ascent + typo_bounds.descent);
+        LayoutRect::new(origin, size)
+    }
+}
+
 trait RangeExt {
     fn intersect(&self, other: &Self) -> Self;
 }
@@ -0,0 +1,115 @@
+// WRTextView/webrender-text-view/src/theme.rs
+//
+// Copyright © 2018 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use pilcrow::Color;
+
+/// The colors a view draws with, where the document itself doesn't specify them.
+#[derive(Clone, Debug)]
+pub struct Theme {
+    pub text_color: Color,
+    /// Painted behind the document. Transparent by default, so the host's clear color shows.
+    pub page_background_color: Color,
+    pub link_color: Color,
+    /// The color of a link while the mouse is down on it.
+    pub active_link_color: Color,
+    /// The color of a link while the mouse is over it.
+    pub hovered_link_color: Color,
+    pub visited_link_color: Color,
+    pub link_underline: LinkUnderline,
+    pub selection_background_color: Color,
+    /// Used for the selection instead when the view isn't focused.
+    pub inactive_selection_background_color: Color,
+    pub rule_color: Color,
+    pub caret_color: Color,
+    /// Used for the dotted underline beneath input method compositions.
+    pub marked_text_underline_color: Color,
+    /// Used for the selection handles drawn on touch devices.
+    pub selection_handle_color: Color,
+    pub find_match_color: Color,
+    pub current_find_match_color: Color,
+}
+
+impl Default for Theme {
+    fn default() -> Theme {
+        Theme {
+            text_color: Color::new(0, 0, 0, 255),
+            page_background_color: Color::new(0, 0, 0, 0),
+            link_color: Color::new(0, 0, 255, 255),
+            active_link_color: Color::new(255, 0, 0, 255),
+            hovered_link_color: Color::new(0, 0, 255, 255),
+            visited_link_color: Color::new(85, 26, 139, 255),
+            link_underline: LinkUnderline::Always,
+            selection_background_color: Color::new(191, 191, 191, 255),
+            inactive_selection_background_color: Color::new(220, 220, 220, 255),
+            rule_color: Color::new(0, 0, 0, 255),
+            caret_color: Color::new(0, 0, 0, 255),
+            marked_text_underline_color: Color::new(0, 0, 0, 255),
+            selection_handle_color: Color::new(0, 122, 255, 255),
+            find_match_color: Color::new(255, 230, 102, 255),
+            current_find_match_color: Color::new(255, 153, 0, 255),
+        }
+    }
+}
+
+/// When links are underlined.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[repr(C)]
+pub enum LinkUnderline {
+    Always = 0,
+    Hover,
+    Never,
+}
+
+/// Names a color in a `Theme`, so that C hosts can set colors one at a time.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[repr(C)]
+pub enum ThemeColor {
+    Text = 0,
+    PageBackground,
+    Link,
+    ActiveLink,
+    VisitedLink,
+    SelectionBackground,
+    Rule,
+    Caret,
+    FindMatch,
+    CurrentFindMatch,
+    InactiveSelectionBackground,
+    HoveredLink,
+    SelectionHandle,
+    MarkedTextUnderline,
+}
+
+impl Theme {
+    pub fn set_color(&mut self, which: ThemeColor, color: Color) {
+        *self.color_mut(which) = color
+    }
+
+    fn color_mut(&mut self, which: ThemeColor) -> &mut Color {
+        match which {
+            ThemeColor::Text => &mut self.text_color,
+            ThemeColor::PageBackground => &mut self.page_background_color,
+            ThemeColor::Link => &mut self.link_color,
+            ThemeColor::ActiveLink => &mut self.active_link_color,
+            ThemeColor::VisitedLink => &mut self.visited_link_color,
+            ThemeColor::SelectionBackground => &mut self.selection_background_color,
+            ThemeColor::InactiveSelectionBackground => {
+                &mut self.inactive_selection_background_color
+            }
+            ThemeColor::Rule => &mut self.rule_color,
+            ThemeColor::Caret => &mut self.caret_color,
+            ThemeColor::FindMatch => &mut self.find_match_color,
+            ThemeColor::CurrentFindMatch => &mut self.current_find_match_color,
+            ThemeColor::HoveredLink => &mut self.hovered_link_color,
+            ThemeColor::SelectionHandle => &mut self.selection_handle_color,
+            ThemeColor::MarkedTextUnderline => &mut self.marked_text_underline_color,
+        }
+    }
+}
@@ -0,0 +1,313 @@
+// WRTextView/webrender-text-view/src/touch.rs
+//
+// Copyright © 2018 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use euclid::TypedVector2D;
+use pilcrow::TextLocation;
+use webrender_api::{LayoutPixel, LayoutPoint};
+use {CaretAffinity, EventResult, Modifiers, MouseEventKind, MouseStatus, SectionExt};
+use {Selection, SelectionDirection, SelectionHandle, View, ViewFlags};
+
+/// How long, in seconds, a touch must be held still to count as a long press.
+pub const LONG_PRESS_DURATION: f64 = 0.5;
+
+// How far, in view coordinates, a touch may wander before it stops being a tap or long press.
+const TOUCH_SLOP: f32 = 10.0;
+// How far, in view coordinates, a touch may land from a selection endpoint and still grab it.
+const SELECTION_END_SLOP: f32 = 20.0;
+
+const MIN_SCALE: f32 = 0.5;
+const MAX_SCALE: f32 = 4.0;
+
+/// Identifies a finger for the lifetime of a touch. Hosts can use any value that is unique among
+/// the touches currently down, such as the address of a `UITouch`.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct TouchId(pub u64);
+
+pub(crate) struct TouchState {
+    touches: Vec<ActiveTouch>,
+    gesture: Gesture,
+}
+
+struct ActiveTouch {
+    id: TouchId,
+    start_point: LayoutPoint,
+    point: LayoutPoint,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Gesture {
+    None,
+    /// One finger is down but hasn't moved far enough or been held long enough to be anything
+    /// else yet.
+    Pending { start_time: f64 },
+    Panning,
+    /// A long press selected a word; moving extends the selection by words.
+    SelectingWords,
+    /// Moving drags the focus of the selection, leaving its anchor alone.
+    DraggingSelectionEnd,
+    /// `focal_point` is the point in the document under the fingers when the pinch began.
+    Pinching { focal_point: LayoutPoint, start_distance: f32, start_scale: f32 },
+    /// The gesture is over, but some fingers are still down. They're ignored until lifted.
+    Finished,
+}
+
+impl TouchState {
+    pub fn new() -> TouchState {
+        TouchState {
+            touches: vec![],
+            gesture: Gesture::None,
+        }
+    }
+
+    fn touch_mut(&mut self, id: TouchId) -> Option<&mut ActiveTouch> {
+        self.touches.iter_mut().find(|touch| touch.id == id)
+    }
+
+    fn remove_touch(&mut self, id: TouchId) -> Option<ActiveTouch> {
+        let index = match self.touches.iter().position(|touch| touch.id == id) {
+            None => return None,
+            Some(index) => index,
+        };
+        Some(self.touches.remove(index))
+    }
+
+    // The midpoint of the first two touches and the distance between them.
+    fn pinch_geometry(&self) -> (LayoutPoint, f32) {
+        let (a, b) = (self.touches[0].point, self.touches[1].point);
+        (a.lerp(b, 0.5), (b - a).length())
+    }
+}
+
+impl View {
+    /// Call this when a finger touches the view, with `point` in view coordinates and `time` in
+    /// seconds from any fixed epoch.
+    ///
+    /// A second finger starts a pinch, which zooms around the point between the fingers. Hosts
+    /// should call `touch_timer_fired()` `LONG_PRESS_DURATION` seconds after a touch begins.
+    pub fn touch_began(&mut self, id: TouchId, point: &LayoutPoint, time: f64) -> EventResult {
+        self.touch_state.touches.push(ActiveTouch {
+            id,
+            start_point: *point,
+            point: *point,
+        });
+
+        match self.touch_state.touches.len() {
+            1 => {
+                self.touch_state.gesture = if self.grab_selection_end(point) {
+                    Gesture::DraggingSelectionEnd
+                } else {
+                    Gesture::Pending { start_time: time }
+                };
+                EventResult::None
+            }
+            2 => {
+                let event_result = self.end_single_touch_gesture();
+                let (midpoint, distance) = self.touch_state.pinch_geometry();
+                self.touch_state.gesture = match self.transform.inverse() {
+                    Some(inverse_transform) if distance > 0.0 => {
+                        Gesture::Pinching {
+                            focal_point: inverse_transform.transform_point(&midpoint),
+                            start_distance: distance,
+                            start_scale: self.transform.m11,
+                        }
+                    }
+                    _ => Gesture::Finished,
+                };
+                event_result
+            }
+            _ => EventResult::None,
+        }
+    }
+
+    /// Call this when a finger moves. Dragging one finger pans, or extends the selection after
+    /// a long press or when it started on a selection end; two fingers pinch to zoom.
+    ///
+    /// Panning and zooming report `TransformChanged`, after which hosts should read back
+    /// `translation()` and `scale()` to sync their scrollers.
+    pub fn touch_moved(&mut self, id: TouchId, point: &LayoutPoint, time: f64) -> EventResult {
+        let (start_point, last_point) = match self.touch_state.touch_mut(id) {
+            None => return EventResult::None,
+            Some(touch) => {
+                let last_point = touch.point;
+                touch.point = *point;
+                (touch.start_point, last_point)
+            }
+        };
+
+        let mut event_result = EventResult::None;
+        if let Gesture::Pending { start_time } = self.touch_state.gesture {
+            if time - start_time >= LONG_PRESS_DURATION {
+                event_result = self.begin_selecting_words(&start_point);
+            } else if (*point - start_point).length() > TOUCH_SLOP {
+                self.touch_state.gesture = Gesture::Panning;
+                return self.pan(&(*point - start_point))
+            } else {
+                return EventResult::None
+            }
+        }
+
+        match self.touch_state.gesture {
+            Gesture::Panning => self.pan(&(*point - last_point)),
+            Gesture::SelectingWords => self.mouse_dragged(point).or(event_result),
+            Gesture::DraggingSelectionEnd => self.drag_selection_end(point),
+            Gesture::Pinching { focal_point, start_distance, start_scale } => {
+                let (midpoint, distance) = self.touch_state.pinch_geometry();
+                let scale = clamp(start_scale * distance / start_distance, MIN_SCALE, MAX_SCALE);
+                self.transform.m11 = scale;
+                self.transform.m22 = scale;
+                self.transform.m31 = midpoint.x - focal_point.x * scale;
+                self.transform.m32 = midpoint.y - focal_point.y * scale;
+                EventResult::TransformChanged
+            }
+            Gesture::None | Gesture::Pending { .. } | Gesture::Finished => event_result,
+        }
+    }
+
+    /// Call this when a finger is lifted. A quick tap acts like a click, activating links and
+    /// placing the caret.
+    pub fn touch_ended(&mut self, id: TouchId, point: &LayoutPoint, time: f64) -> EventResult {
+        if self.touch_state.remove_touch(id).is_none() {
+            return EventResult::None
+        }
+
+        let event_result = match self.touch_state.gesture {
+            Gesture::Pending { start_time } if time - start_time >= LONG_PRESS_DURATION => {
+                self.begin_selecting_words(point).or(self.end_single_touch_gesture())
+            }
+            Gesture::Pending { .. } => {
+                let down_event_result =
+                    self.mouse_down(point, MouseEventKind::Left, Modifiers::empty());
+                self.mouse_up(point, MouseEventKind::Left, Modifiers::empty())
+                    .or(down_event_result)
+            }
+            _ => self.end_single_touch_gesture(),
+        };
+
+        self.touch_state.gesture = if self.touch_state.touches.is_empty() {
+            Gesture::None
+        } else {
+            Gesture::Finished
+        };
+        event_result
+    }
+
+    /// Call this when the system takes a touch away, for example because a scroll view claimed
+    /// it. Selection changes already made are kept.
+    pub fn touch_cancelled(&mut self, id: TouchId) -> EventResult {
+        if self.touch_state.remove_touch(id).is_none() {
+            return EventResult::None
+        }
+        let event_result = self.end_single_touch_gesture();
+        self.touch_state.gesture = if self.touch_state.touches.is_empty() {
+            Gesture::None
+        } else {
+            Gesture::Finished
+        };
+        event_result
+    }
+
+    /// Recognizes a long press if a touch has been held still long enough.
+    pub fn touch_timer_fired(&mut self, time: f64) -> EventResult {
+        match (self.touch_state.gesture, self.touch_state.touches.first()) {
+            (Gesture::Pending { start_time }, Some(touch))
+                    if time - start_time >= LONG_PRESS_DURATION => {
+                let point = touch.start_point;
+                self.begin_selecting_words(&point)
+            }
+            _ => EventResult::None,
+        }
+    }
+
+    fn begin_selecting_words(&mut self, point: &LayoutPoint) -> EventResult {
+        self.touch_state.gesture = Gesture::SelectingWords;
+        self.mouse_down(point, MouseEventKind::LeftDouble, Modifiers::empty())
+    }
+
+    // Finishes any gesture that is driving the mouse machinery, as if the button were released.
+    fn end_single_touch_gesture(&mut self) -> EventResult {
+        if let Gesture::SelectingWords = self.touch_state.gesture {
+            self.mouse_status = MouseStatus::Up;
+            self.active_link_id = None;
+            self.rebuild_display_list();
+        }
+        self.touch_state.gesture = Gesture::Finished;
+        EventResult::None
+    }
+
+    fn pan(&mut self, delta: &TypedVector2D<f32, LayoutPixel>) -> EventResult {
+        self.transform.m31 += delta.x;
+        self.transform.m32 += delta.y;
+        EventResult::TransformChanged
+    }
+
+    // If `point` is near an end of the selection, or on its handle, makes that end the focus and
+    // returns true.
+    fn grab_selection_end(&mut self, point: &LayoutPoint) -> bool {
+        let range = match self.selection {
+            Some(ref selection) if !selection.is_collapsed() => selection.range.clone(),
+            _ => return false,
+        };
+        let direction = if self.is_near_selection_end(point, &range.end, SelectionHandle::End) {
+            SelectionDirection::Forward
+        } else if self.is_near_selection_end(point, &range.start, SelectionHandle::Start) {
+            SelectionDirection::Backward
+        } else {
+            return false
+        };
+        self.selection = Some(Selection {
+            range,
+            direction,
+            affinity: CaretAffinity::Downstream,
+        });
+        true
+    }
+
+    fn drag_selection_end(&mut self, point: &LayoutPoint) -> EventResult {
+        let location = match self.text_at_point(point) {
+            None => return EventResult::None,
+            Some((location, _)) => location,
+        };
+        let selection_before = self.selection.clone();
+        let selection = match self.selection {
+            None => return EventResult::None,
+            Some(ref selection) => selection.extended_to(location),
+        };
+        // Never let the dragged end cross the anchor and collapse the selection.
+        if selection.is_collapsed() {
+            return EventResult::None
+        }
+        self.selection = Some(selection);
+        self.rebuild_display_list();
+        self.selection_changed_event(&selection_before)
+    }
+
+    fn is_near_selection_end(&self,
+                             point: &LayoutPoint,
+                             location: &TextLocation,
+                             handle: SelectionHandle)
+                             -> bool {
+        let bounds = if self.flags.contains(ViewFlags::SELECTION_HANDLES) {
+            self.section.selection_handle_bounds(location, handle).map(|bounds| bounds.union())
+        } else {
+            self.section.caret_bounds(location, CaretAffinity::Downstream, 0.0)
+        };
+        match bounds {
+            None => false,
+            Some(bounds) => {
+                let bounds = self.transform.transform_rect(&bounds);
+                bounds.inflate(SELECTION_END_SLOP, SELECTION_END_SLOP).contains(point)
+            }
+        }
+    }
+}
+
+fn clamp(value: f32, min: f32, max: f32) -> f32 {
+    value.max(min).min(max)
+}
@@ -0,0 +1,264 @@
+// WRTextView/webrender-text-view/src/undo.rs
+//
+// Copyright © 2018 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use pilcrow::{Document, Format, TextLocation};
+use std::ops::Range;
+use {Selection, location_after_text};
+
+/// Replaces the content of `range` with `inserted`, a fragment of rich text. Inserts and deletes
+/// are the special cases where the range or the fragment is empty, and formatting changes replace
+/// text with the same text formatted differently.
+///
+/// Both sides are kept as fragments, so undoing restores formatting, links and images too.
+#[derive(Clone, Debug)]
+pub(crate) struct Edit {
+    kind: EditKind,
+    range: Range<TextLocation>,
+    removed: Document,
+    inserted: Document,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum EditKind {
+    Text,
+    /// Only the formatting changed, so locations stay where they are.
+    Formatting,
+}
+
+impl Edit {
+    /// Replaces the text in `range` with `text` and returns the edit that did it. The new text
+    /// takes on the formatting at the start of the range.
+    pub fn replace_text(document: &mut Document, range: Range<TextLocation>, text: &str)
+                        -> Edit {
+        let removed = document.copy_range(range.clone());
+        if range.start != range.end {
+            document.delete_range(range.clone());
+        }
+        if !text.is_empty() {
+            document.insert_string(range.start, text);
+        }
+        let inserted_end = location_after_text(&range.start, text);
+        Edit {
+            kind: EditKind::Text,
+            inserted: document.copy_range(range.start..inserted_end),
+            removed,
+            range,
+        }
+    }
+
+    /// Adds `format` to the text in `range` and returns the edit that did it.
+    pub fn add_format(document: &mut Document, range: Range<TextLocation>, format: Format)
+                      -> Edit {
+        let removed = document.copy_range(range.clone());
+        document.add_format(range.clone(), format);
+        Edit {
+            kind: EditKind::Formatting,
+            inserted: document.copy_range(range.clone()),
+            removed,
+            range,
+        }
+    }
+
+    pub fn apply(&self, document: &mut Document) {
+        if self.range.start != self.range.end {
+            document.delete_range(self.range.clone());
+        }
+        document.insert_document(self.range.start, &self.inserted);
+    }
+
+    pub fn inverse(&self) -> Edit {
+        Edit {
+            kind: self.kind,
+            range: self.range.start..self.inserted_end(),
+            removed: self.inserted.clone(),
+            inserted: self.removed.clone(),
+        }
+    }
+
+    /// The location just after the inserted content, once the edit has been applied.
+    pub fn inserted_end(&self) -> TextLocation {
+        let paragraphs = self.inserted.paragraphs();
+        match paragraphs.len() {
+            0 => self.range.start,
+            1 => {
+                TextLocation::new(self.range.start.paragraph_index,
+                                  self.range.start.character_index + paragraphs[0].char_len())
+            }
+            paragraph_count => {
+                TextLocation::new(self.range.start.paragraph_index + paragraph_count - 1,
+                                  paragraphs[paragraph_count - 1].char_len())
+            }
+        }
+    }
+
+    /// Like `transform_location()`, but text inserted exactly at `location` ends up before it.
+    /// Use this for the starts of ranges, so that they don't grow at the front.
+    pub fn transform_range_start(&self, location: &TextLocation) -> TextLocation {
+        if self.kind == EditKind::Text && self.range.start == self.range.end &&
+                *location == self.range.start {
+            return self.inserted_end()
+        }
+        self.transform_location(location)
+    }
+
+    /// Maps a location from before the edit to the equivalent location after it. Locations
+    /// inside the replaced range move to its start.
+    pub fn transform_location(&self, location: &TextLocation) -> TextLocation {
+        if self.kind == EditKind::Formatting || *location <= self.range.start {
+            return *location
+        }
+        if *location < self.range.end {
+            return self.range.start
+        }
+
+        let inserted_end = self.inserted_end();
+        if location.paragraph_index == self.range.end.paragraph_index {
+            return TextLocation::new(inserted_end.paragraph_index,
+                                     inserted_end.character_index + location.character_index -
+                                     self.range.end.character_index)
+        }
+        let paragraph_index = location.paragraph_index + inserted_end.paragraph_index -
+            self.range.end.paragraph_index;
+        TextLocation::new(paragraph_index, location.character_index)
+    }
+}
+
+/// Edits that are undone and redone together, along with the selection to restore on undo.
+struct UndoGroup {
+    edits: Vec<Edit>,
+    selection_before: Option<Selection>,
+    /// Where the text that the group's edits inserted, minus what they deleted again, is now.
+    inserted_range: Range<TextLocation>,
+}
+
+/// What an undo or redo did: the edits it applied, in order, and the selection to restore.
+pub(crate) struct HistoryChange {
+    pub applied_edits: Vec<Edit>,
+    pub selection: Option<Selection>,
+}
+
+pub(crate) struct UndoManager {
+    undo_stack: Vec<UndoGroup>,
+    redo_stack: Vec<UndoGroup>,
+    // Whether the next coalescable edit may join the group on top of the undo stack.
+    group_open: bool,
+}
+
+impl UndoManager {
+    pub fn new() -> UndoManager {
+        UndoManager {
+            undo_stack: vec![],
+            redo_stack: vec![],
+            group_open: false,
+        }
+    }
+
+    /// Records an edit that has just been applied.
+    ///
+    /// If `coalesce` is true and the edit only touches text inserted by the group on top of the
+    /// undo stack, as typing and backspacing over what was just typed do, it joins that group.
+    pub fn record(&mut self, edit: Edit, selection_before: Option<Selection>, coalesce: bool) {
+        self.redo_stack.clear();
+
+        if coalesce && self.group_open {
+            if let Some(group) = self.undo_stack.last_mut() {
+                if edit.range.start >= group.inserted_range.start &&
+                        edit.range.end <= group.inserted_range.end {
+                    group.inserted_range.end = if edit.range.end == group.inserted_range.end {
+                        edit.inserted_end()
+                    } else {
+                        edit.transform_location(&group.inserted_range.end)
+                    };
+                    group.edits.push(edit);
+                    return
+                }
+            }
+        }
+
+        self.undo_stack.push(UndoGroup {
+            inserted_range: edit.range.start..edit.inserted_end(),
+            edits: vec![edit],
+            selection_before,
+        });
+        self.group_open = coalesce;
+    }
+
+    /// Ends the current typing group, so the next edit starts a new one. Call this when the
+    /// selection moves.
+    #[inline]
+    pub fn break_coalescing(&mut self) {
+        self.group_open = false
+    }
+
+    /// Removes everything. Call this when the document changes behind our back, since recorded
+    /// locations may no longer be valid.
+    pub fn clear(&mut self) {
+        self.undo_stack.clear();
+        self.redo_stack.clear();
+        self.group_open = false;
+    }
+
+    #[inline]
+    pub fn can_undo(&self) -> bool {
+        !self.undo_stack.is_empty()
+    }
+
+    #[inline]
+    pub fn can_redo(&self) -> bool {
+        !self.redo_stack.is_empty()
+    }
+
+    /// Reverts the most recent group of edits.
+    pub fn undo(&mut self, document: &mut Document) -> Option<HistoryChange> {
+        let group = match self.undo_stack.pop() {
+            None => return None,
+            Some(group) => group,
+        };
+        let applied_edits: Vec<Edit> = group.edits.iter().rev().map(Edit::inverse).collect();
+        for edit in &applied_edits {
+            edit.apply(document)
+        }
+
+        let selection = group.selection_before.clone();
+        self.redo_stack.push(group);
+        self.group_open = false;
+        Some(HistoryChange {
+            applied_edits,
+            selection,
+        })
+    }
+
+    /// Reapplies the most recently undone group of edits.
+    pub fn redo(&mut self, document: &mut Document) -> Option<HistoryChange> {
+        let group = match self.redo_stack.pop() {
+            None => return None,
+            Some(group) => group,
+        };
+        for edit in &group.edits {
+            edit.apply(document)
+        }
+
+        let applied_edits = group.edits.clone();
+        // Redoing a formatting change selects the text it changed, since it has no caret to
+        // restore.
+        let selection = group.edits.last().map(|edit| {
+            match edit.kind {
+                EditKind::Text => Selection::caret(edit.inserted_end()),
+                EditKind::Formatting => Selection::forward(edit.range.start..edit.inserted_end()),
+            }
+        });
+        self.undo_stack.push(group);
+        self.group_open = false;
+        Some(HistoryChange {
+            applied_edits,
+            selection,
+        })
+    }
+}
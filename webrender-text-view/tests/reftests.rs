@@ -0,0 +1,286 @@
+// WRTextView/webrender-text-view/tests/reftests.rs
+//
+// Copyright © 2018 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Renders the documents listed in `tests/reftests/reftest.list` headlessly and compares them
+//! against the reference images next to them.
+//!
+//! Set `WRTV_UPDATE_REFTESTS` to write the rendered images as the new references instead. Without
+//! it, any missing reference fails the whole suite before anything is rendered.
+
+extern crate euclid;
+extern crate pilcrow;
+extern crate png;
+extern crate webrender_api;
+extern crate webrender_text_view;
+
+use euclid::{Length, Size2D, TypedScale};
+use pilcrow::{Document, MarkdownParser};
+use png::HasParameters;
+use std::env;
+use std::fs::{self, File};
+use std::io::{BufWriter, Read};
+use std::path::{Path, PathBuf};
+use webrender_api::{DeviceUintSize, LayoutPoint};
+use webrender_text_view::{ImageId, Modifiers, MouseEventKind, View, ViewFlags};
+
+const VIEWPORT_WIDTH: u32 = 400;
+const VIEWPORT_HEIGHT: u32 = 300;
+
+const IMAGE_COLOR: [u8; 4] = [0x80, 0x40, 0xc0, 0xff];
+
+#[test]
+fn reftests() {
+    let reftest_dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests").join("reftests");
+    let update = env::var("WRTV_UPDATE_REFTESTS").is_ok();
+
+    let mut manifest = String::new();
+    File::open(reftest_dir.join("reftest.list")).unwrap()
+                                                .read_to_string(&mut manifest)
+                                                .unwrap();
+
+    let mut reftests = vec![];
+    for (line_number, line) in manifest.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue
+        }
+
+        reftests.push(Reftest::parse(&reftest_dir, line).unwrap_or_else(|message| {
+            panic!("reftest.list:{}: {}", line_number + 1, message)
+        }));
+    }
+
+    // Report every missing reference up front, before spending time rendering the rest.
+    if !update {
+        let missing: Vec<_> = reftests.iter()
+                                      .filter(|reftest| !reftest.reference.exists())
+                                      .map(|reftest| reftest.reference.display().to_string())
+                                      .collect();
+        if !missing.is_empty() {
+            panic!("{} reference image(s) missing:\n{}\nRun `WRTV_UPDATE_REFTESTS=1 cargo test \
+                    --features headless --test reftests` to create them, then check them in.",
+                   missing.len(),
+                   missing.join("\n"))
+        }
+    }
+
+    let mut failures = vec![];
+    for reftest in &reftests {
+        if let Err(message) = reftest.run(update) {
+            failures.push(format!("{}: {}", reftest.reference.display(), message))
+        }
+    }
+
+    if !failures.is_empty() {
+        panic!("{} reftest(s) failed:\n{}", failures.len(), failures.join("\n"))
+    }
+}
+
+struct Reftest {
+    source: PathBuf,
+    reference: PathBuf,
+    fuzz: Fuzz,
+    actions: Vec<Action>,
+}
+
+#[derive(Clone, Copy)]
+struct Fuzz {
+    max_difference: u8,
+    num_differences: usize,
+}
+
+enum Action {
+    SelectAll,
+    Drag(LayoutPoint, LayoutPoint),
+    Image(u32, Size2D<u32>),
+}
+
+impl Reftest {
+    fn parse(reftest_dir: &Path, line: &str) -> Result<Reftest, String> {
+        let mut fuzz = Fuzz {
+            max_difference: 0,
+            num_differences: 0,
+        };
+        let mut actions = vec![];
+
+        let mut words = line.split_whitespace();
+        loop {
+            let word = match words.next() {
+                None => return Err("expected `==`".to_owned()),
+                Some("==") => break,
+                Some(word) => word,
+            };
+
+            let (name, args) = parse_call(word)?;
+            match (name, args.len()) {
+                ("fuzzy", 2) => {
+                    fuzz = Fuzz {
+                        max_difference: args[0] as u8,
+                        num_differences: args[1] as usize,
+                    }
+                }
+                ("select-all", 0) => actions.push(Action::SelectAll),
+                ("drag", 4) => {
+                    actions.push(Action::Drag(LayoutPoint::new(args[0], args[1]),
+                                              LayoutPoint::new(args[2], args[3])))
+                }
+                ("image", 3) => {
+                    actions.push(Action::Image(args[0] as u32,
+                                               Size2D::new(args[1] as u32, args[2] as u32)))
+                }
+                _ => return Err(format!("unknown option `{}`", word)),
+            }
+        }
+
+        match (words.next(), words.next(), words.next()) {
+            (Some(source), Some(reference), None) => {
+                Ok(Reftest {
+                    source: reftest_dir.join(source),
+                    reference: reftest_dir.join(reference),
+                    fuzz,
+                    actions,
+                })
+            }
+            _ => Err("expected a source and a reference after `==`".to_owned()),
+        }
+    }
+
+    fn run(&self, update: bool) -> Result<(), String> {
+        let pixels = self.render();
+        if update {
+            write_png(&self.reference, &pixels);
+            eprintln!("wrote {}", self.reference.display());
+            return Ok(())
+        }
+
+        let reference = match read_png(&self.reference) {
+            None => return Err("couldn't read the reference image".to_owned()),
+            Some(reference) => reference,
+        };
+
+        let (mut max_difference, mut num_differences) = (0, 0);
+        for (actual, expected) in pixels.chunks(4).zip(reference.chunks(4)) {
+            let difference = actual.iter()
+                                   .zip(expected.iter())
+                                   .map(|(&a, &b)| (a as i32 - b as i32).abs() as u8)
+                                   .max()
+                                   .unwrap();
+            if difference > 0 {
+                max_difference = max_difference.max(difference);
+                num_differences += 1;
+            }
+        }
+
+        if max_difference <= self.fuzz.max_difference &&
+                num_differences <= self.fuzz.num_differences {
+            return Ok(())
+        }
+
+        let actual_path = self.actual_path();
+        write_png(&actual_path, &pixels);
+        Err(format!("{} pixel(s) differ by up to {} (allowed: {} by up to {}); see {}",
+                    num_differences,
+                    max_difference,
+                    self.fuzz.num_differences,
+                    self.fuzz.max_difference,
+                    actual_path.display()))
+    }
+
+    fn render(&self) -> Vec<u8> {
+        let mut source = String::new();
+        File::open(&self.source).unwrap().read_to_string(&mut source).unwrap();
+        let mut document = Document::new();
+        MarkdownParser::new().add_to_document(&source, &mut document);
+
+        let mut view = View::new_headless(document,
+                                          &DeviceUintSize::new(VIEWPORT_WIDTH, VIEWPORT_HEIGHT),
+                                          TypedScale::new(1.0),
+                                          Length::new(VIEWPORT_WIDTH as f32),
+                                          ViewFlags::empty());
+
+        for action in &self.actions {
+            match *action {
+                Action::SelectAll => {
+                    view.select_all();
+                }
+                Action::Drag(ref from, ref to) => {
+                    view.mouse_down(from, MouseEventKind::Left, Modifiers::empty());
+                    view.mouse_dragged(to);
+                    view.mouse_up(to, MouseEventKind::Left, Modifiers::empty());
+                }
+                Action::Image(id, ref size) => {
+                    let pixel_count = size.width as usize * size.height as usize;
+                    let data = IMAGE_COLOR.iter().cloned().cycle().take(pixel_count * 4).collect();
+                    view.set_image_size(ImageId(id), size);
+                    view.set_image_data(ImageId(id), data);
+                }
+            }
+        }
+
+        let stride = VIEWPORT_WIDTH as usize * 4;
+        let mut pixels = vec![0; stride * VIEWPORT_HEIGHT as usize];
+        assert!(view.render_to_buffer(&mut pixels, stride));
+        pixels
+    }
+
+    fn actual_path(&self) -> PathBuf {
+        let output_dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("target").join("reftests");
+        fs::create_dir_all(&output_dir).unwrap();
+        let file_stem = self.reference.file_stem().unwrap().to_string_lossy();
+        output_dir.join(format!("{}-actual.png", file_stem))
+    }
+}
+
+/// Parses `name(arg,arg,...)` or a bare `name`.
+fn parse_call(word: &str) -> Result<(&str, Vec<f32>), String> {
+    let open = match word.find('(') {
+        None => return Ok((word, vec![])),
+        Some(open) => open,
+    };
+    if !word.ends_with(')') {
+        return Err(format!("missing `)` in `{}`", word))
+    }
+
+    let mut args = vec![];
+    for arg in word[(open + 1)..(word.len() - 1)].split(',') {
+        match arg.trim().parse() {
+            Ok(arg) => args.push(arg),
+            Err(_) => return Err(format!("bad argument `{}` in `{}`", arg, word)),
+        }
+    }
+    Ok((&word[..open], args))
+}
+
+fn read_png(path: &Path) -> Option<Vec<u8>> {
+    let file = match File::open(path) {
+        Err(_) => return None,
+        Ok(file) => file,
+    };
+    let (info, mut reader) = png::Decoder::new(file).read_info().unwrap();
+    assert!(info.color_type == png::ColorType::RGBA && info.bit_depth == png::BitDepth::Eight,
+            "{} must be an 8-bit RGBA image!",
+            path.display());
+    assert!(info.width == VIEWPORT_WIDTH && info.height == VIEWPORT_HEIGHT,
+            "{} must be {}x{}!",
+            path.display(),
+            VIEWPORT_WIDTH,
+            VIEWPORT_HEIGHT);
+
+    let mut pixels = vec![0; info.buffer_size()];
+    reader.next_frame(&mut pixels).unwrap();
+    Some(pixels)
+}
+
+fn write_png(path: &Path, pixels: &[u8]) {
+    let writer = BufWriter::new(File::create(path).unwrap());
+    let mut encoder = png::Encoder::new(writer, VIEWPORT_WIDTH, VIEWPORT_HEIGHT);
+    encoder.set(png::ColorType::RGBA).set(png::BitDepth::Eight);
+    encoder.write_header().unwrap().write_image_data(pixels).unwrap();
+}
@@ -0,0 +1,633 @@
+// WRTextView/webrender-text-view/tests/view.rs
+//
+// Copyright © 2018 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+extern crate euclid;
+extern crate pilcrow;
+extern crate webrender_api;
+extern crate webrender_text_view;
+
+use euclid::{Length, Point2D, Rect, Size2D, TypedScale, TypedSideOffsets2D, Vector2D};
+use pilcrow::{Color, Decoration, Document, Format, Framesetter, MarkdownParser, Shadow};
+use pilcrow::TextLocation;
+use std::rc::Rc;
+use webrender_api::{DeviceUintSize, LayoutPoint};
+use webrender_text_view::{DefaultFontBackend, EventResult, FakeResourceApi, FindOptions};
+use webrender_text_view::{FontBackend, Highlight, HighlightStyle};
+use webrender_text_view::{Key, LinkUnderline, Modifiers, MouseCursor, MouseEventKind, Theme};
+use webrender_text_view::{PointInfo, TouchId, View, ViewFlags};
+
+fn view_for_markdown(markdown: &str) -> (View, Rc<FakeResourceApi>) {
+    view_for_markdown_with_flags(markdown, ViewFlags::empty())
+}
+
+fn view_for_markdown_with_flags(markdown: &str, flags: ViewFlags)
+                                -> (View, Rc<FakeResourceApi>) {
+    let mut document = Document::new();
+    MarkdownParser::new().add_to_document(markdown, &mut document);
+    let resource_api = Rc::new(FakeResourceApi::new());
+    let view = View::with_resource_api(document,
+                                       &DeviceUintSize::new(400, 300),
+                                       TypedScale::new(1.0),
+                                       Length::new(400.0),
+                                       Box::new(resource_api.clone()),
+                                       flags);
+    (view, resource_api)
+}
+
+#[test]
+fn fonts_are_registered_once() {
+    let (mut view, resource_api) = view_for_markdown("Hello world.");
+    assert!(!resource_api.resource_updates()[0].updates.is_empty());
+
+    view.select_all();
+    assert!(resource_api.resource_updates().last().unwrap().updates.is_empty());
+}
+
+#[test]
+fn the_font_backend_looks_up_glyphs() {
+    let mut document = Document::new();
+    MarkdownParser::new().add_to_document("Hello world.", &mut document);
+    let layout_rect = Rect::new(Point2D::zero(), Size2D::new(400.0, 1000000.0));
+    let section = Framesetter::new(&document).layout_in_rect(&layout_rect, None);
+    let formatting = section.frames()[0].lines()[0].runs()[0].formatting();
+    let font = formatting.into_iter().filter_map(|format| format.font()).next().unwrap();
+
+    let font_backend = DefaultFontBackend::new();
+    let native_font_handle = font_backend.native_font_handle(&font);
+    let glyph_index = font_backend.glyph_index(&native_font_handle, 'H');
+    assert!(glyph_index.is_some());
+    assert_ne!(glyph_index, font_backend.glyph_index(&native_font_handle, 'w'));
+}
+
+#[test]
+fn select_all_copies_everything() {
+    let (mut view, _) = view_for_markdown("Hello world.\n\nGoodbye.");
+    assert_eq!(view.copy_selected_text(), None);
+    view.select_all();
+    assert_eq!(view.copy_selected_text(), Some("Hello world.\nGoodbye.".to_owned()));
+}
+
+#[test]
+fn double_click_selects_word() {
+    let (mut view, _) = view_for_markdown("Hello world.");
+    let point = LayoutPoint::new(2.0, 2.0);
+    view.mouse_down(&point, MouseEventKind::LeftDouble, Modifiers::empty());
+    view.mouse_up(&point, MouseEventKind::LeftDouble, Modifiers::empty());
+    assert_eq!(view.copy_selected_text(), Some("Hello".to_owned()));
+}
+
+#[test]
+fn links_show_pointer_cursor() {
+    let (view, _) = view_for_markdown("[Hello](https://example.com/) world.");
+    assert!(view.get_mouse_cursor(&LayoutPoint::new(2.0, 2.0)) == MouseCursor::Pointer);
+    assert!(view.get_mouse_cursor(&LayoutPoint::new(2.0, 1000.0)) == MouseCursor::Default);
+}
+
+#[test]
+fn solid_underlines_dump_their_thickness() {
+    let (view, _) = view_for_markdown("[Hello](https://example.com/) world.");
+    let dump = view.dump_display_list();
+    assert!(dump.contains("\"style\": \"Solid\""));
+    assert!(!dump.contains("\"thickness\": 0.00"));
+}
+
+#[test]
+fn hovering_a_link_restyles_it() {
+    let (mut view, resource_api) = view_for_markdown("[Hello](https://example.com/) world.");
+    let mut theme = Theme::default();
+    theme.hovered_link_color = Color::new(255, 0, 255, 255);
+    theme.link_underline = LinkUnderline::Hover;
+    view.set_theme(theme);
+    assert!(!view.dump_display_list().contains("[1.000, 0.000, 1.000, 1.000]"));
+    assert!(!view.dump_display_list().contains("\"type\": \"line\""));
+
+    view.mouse_moved(&LayoutPoint::new(2.0, 2.0));
+    assert!(view.dump_display_list().contains("[1.000, 0.000, 1.000, 1.000]"));
+    assert!(view.dump_display_list().contains("\"type\": \"line\""));
+
+    // Moving within the same link shouldn't rebuild the display list.
+    let transaction_count = resource_api.transaction_count();
+    view.mouse_moved(&LayoutPoint::new(4.0, 2.0));
+    assert_eq!(resource_api.transaction_count(), transaction_count);
+
+    view.mouse_exited();
+    assert!(!view.dump_display_list().contains("[1.000, 0.000, 1.000, 1.000]"));
+}
+
+#[test]
+fn the_caret_blinks_and_hides_when_unfocused() {
+    let (mut view, _) = view_for_markdown_with_flags("Hello world.", ViewFlags::EDITABLE);
+    view.set_caret_color(Color::new(255, 0, 255, 255));
+    view.key_down(Key::Right, Modifiers::empty());
+    assert!(view.has_caret());
+    assert!(view.dump_display_list().contains("[1.000, 0.000, 1.000, 1.000]"));
+
+    view.set_caret_blink_phase(false);
+    assert!(!view.dump_display_list().contains("[1.000, 0.000, 1.000, 1.000]"));
+    view.set_caret_blink_phase(true);
+    assert!(view.dump_display_list().contains("[1.000, 0.000, 1.000, 1.000]"));
+
+    view.set_focused(false);
+    assert!(!view.has_caret());
+    assert!(!view.dump_display_list().contains("[1.000, 0.000, 1.000, 1.000]"));
+}
+
+#[test]
+fn read_only_views_have_no_caret() {
+    let (mut view, _) = view_for_markdown("Hello world.");
+    view.set_caret_color(Color::new(255, 0, 255, 255));
+    view.key_down(Key::Right, Modifiers::empty());
+    assert!(!view.has_caret());
+    assert!(!view.dump_display_list().contains("[1.000, 0.000, 1.000, 1.000]"));
+}
+
+#[test]
+fn input_reports_events() {
+    let (mut view, _) = view_for_markdown_with_flags("[Hello](https://example.com/) world.",
+                                                     ViewFlags::EDITABLE);
+    let link_point = LayoutPoint::new(2.0, 2.0);
+    assert_eq!(view.mouse_moved(&link_point),
+               EventResult::LinkHoverEnter("https://example.com/".to_owned()));
+    assert_eq!(view.mouse_moved(&link_point), EventResult::None);
+    assert_eq!(view.mouse_exited(),
+               EventResult::LinkHoverLeave("https://example.com/".to_owned()));
+
+    match view.mouse_down(&link_point, MouseEventKind::Right, Modifiers::empty()) {
+        EventResult::ContextMenu(point_info) => {
+            assert_eq!(point_info.url, Some("https://example.com/".to_owned()));
+            assert_eq!(point_info.image_id, None);
+            assert_eq!(point_info.word_range,
+                       Some(TextLocation::new(0, 0)..TextLocation::new(0, 5)));
+            assert!(!point_info.in_selection);
+        }
+        event_result => panic!("expected a context menu event, got {:?}", event_result),
+    }
+    assert_eq!(view.select_all(), EventResult::SelectionChanged);
+    assert_eq!(view.select_all(), EventResult::None);
+    assert_eq!(view.replace_selection("Goodbye"), EventResult::DocumentEdited);
+    assert_eq!(view.key_down(Key::DocumentStart, Modifiers::empty()),
+               EventResult::SelectionChanged);
+}
+
+#[test]
+fn inspect_point_describes_links_and_words() {
+    let (mut view, _) = view_for_markdown("[Hello](https://example.com/) world.");
+    let point_info = view.inspect_point(&LayoutPoint::new(2.0, 2.0));
+    assert_eq!(point_info.url, Some("https://example.com/".to_owned()));
+    assert_eq!(point_info.word_range, Some(TextLocation::new(0, 0)..TextLocation::new(0, 5)));
+    assert!(!point_info.in_selection);
+
+    view.select_all();
+    assert!(view.inspect_point(&LayoutPoint::new(2.0, 2.0)).in_selection);
+    assert_eq!(view.inspect_point(&LayoutPoint::new(2.0, 1000.0)), PointInfo::default());
+}
+
+#[test]
+fn shift_click_extends_selection_from_anchor() {
+    let (mut view, _) = view_for_markdown_with_flags("Hello world.", ViewFlags::EDITABLE);
+    view.key_down(Key::WordRight, Modifiers::empty());
+    let point = LayoutPoint::new(2.0, 2.0);
+    view.mouse_down(&point, MouseEventKind::Left, Modifiers::SHIFT);
+    view.mouse_up(&point, MouseEventKind::Left, Modifiers::SHIFT);
+    let selected_text = view.copy_selected_text().unwrap();
+    assert!(!selected_text.is_empty() && "Hello".ends_with(&selected_text));
+
+    view.key_down(Key::Right, Modifiers::SHIFT);
+    assert!(view.copy_selected_text().unwrap().len() < selected_text.len());
+}
+
+#[test]
+fn link_activation_reports_modifiers() {
+    let (mut view, _) = view_for_markdown("[Hello](https://example.com/) world.");
+    let point = LayoutPoint::new(2.0, 2.0);
+    view.mouse_down(&point, MouseEventKind::Left, Modifiers::META);
+    assert_eq!(view.mouse_up(&point, MouseEventKind::Left, Modifiers::META),
+               EventResult::OpenUrl("https://example.com/".to_owned(), Modifiers::META));
+}
+
+#[test]
+fn taps_activate_links_and_long_presses_select_words() {
+    let (mut view, _) = view_for_markdown("[Hello](https://example.com/) world.");
+    let point = LayoutPoint::new(2.0, 2.0);
+    view.touch_began(TouchId(1), &point, 0.0);
+    assert_eq!(view.touch_ended(TouchId(1), &point, 0.1),
+               EventResult::OpenUrl("https://example.com/".to_owned(), Modifiers::empty()));
+
+    view.touch_began(TouchId(2), &point, 1.0);
+    assert_eq!(view.touch_timer_fired(2.0), EventResult::SelectionChanged);
+    view.touch_ended(TouchId(2), &point, 2.1);
+    assert_eq!(view.copy_selected_text(), Some("Hello".to_owned()));
+}
+
+#[test]
+fn pinching_zooms_around_the_focal_point() {
+    let (mut view, _) = view_for_markdown("Hello world.");
+    view.touch_began(TouchId(1), &LayoutPoint::new(90.0, 50.0), 0.0);
+    view.touch_began(TouchId(2), &LayoutPoint::new(110.0, 50.0), 0.0);
+    assert_eq!(view.touch_moved(TouchId(2), &LayoutPoint::new(130.0, 50.0), 0.1),
+               EventResult::TransformChanged);
+    view.touch_ended(TouchId(1), &LayoutPoint::new(90.0, 50.0), 0.2);
+    view.touch_ended(TouchId(2), &LayoutPoint::new(130.0, 50.0), 0.2);
+
+    // The document point under the original midpoint, (100, 50), is now under the new midpoint,
+    // (110, 50), at twice the scale.
+    assert!((view.scale() - 2.0).abs() < 0.01);
+    let translation = view.translation();
+    assert!((translation.x - (100.0 * 2.0 - 110.0)).abs() < 0.01);
+    assert!((translation.y - (50.0 * 2.0 - 50.0)).abs() < 0.01);
+}
+
+#[test]
+fn selection_handles_are_drawn_and_dragged() {
+    let (mut view, _) = view_for_markdown_with_flags("Hello world.", ViewFlags::SELECTION_HANDLES);
+    view.select_all();
+    assert!(view.dump_display_list().contains("[0.000, 0.478, 1.000, 1.000]"));
+
+    // Grab the end handle by its knob, below the line, and drag it back into the text.
+    let end_bounds = view.caret_rect().unwrap();
+    let knob_point = LayoutPoint::new(end_bounds.origin.x, end_bounds.max_y() + 6.0);
+    let drag_point = LayoutPoint::new(end_bounds.origin.x * 0.5, end_bounds.origin.y + 2.0);
+    view.touch_began(TouchId(1), &knob_point, 0.0);
+    assert_eq!(view.touch_moved(TouchId(1), &drag_point, 0.1), EventResult::SelectionChanged);
+    view.touch_ended(TouchId(1), &drag_point, 0.2);
+
+    let selected_text = view.copy_selected_text().unwrap();
+    assert!(!selected_text.is_empty() && selected_text.len() < "Hello world.".len());
+    assert!("Hello world.".starts_with(&selected_text));
+}
+
+#[test]
+fn typing_replaces_selection() {
+    let (mut view, _) = view_for_markdown_with_flags("Hello world.", ViewFlags::EDITABLE);
+    view.select_all();
+    view.replace_selection("Goodbye");
+    view.replace_selection(" now");
+    view.select_all();
+    assert_eq!(view.copy_selected_text(), Some("Goodbye now".to_owned()));
+}
+
+#[test]
+fn delete_backward_joins_paragraphs() {
+    let (mut view, _) = view_for_markdown_with_flags("Hello\n\nworld", ViewFlags::EDITABLE);
+    view.insert_text(&TextLocation::new(1, 0), "x");
+    view.delete_backward();
+    view.delete_backward();
+    view.select_all();
+    assert_eq!(view.copy_selected_text(), Some("Helloworld".to_owned()));
+}
+
+#[test]
+fn edits_clamp_locations_and_respect_editable() {
+    let (mut view, _) = view_for_markdown("Hello");
+    assert_eq!(view.insert_text(&TextLocation::new(0, 0), "Oh, "), EventResult::None);
+    view.select_all();
+    assert_eq!(view.copy_selected_text(), Some("Hello".to_owned()));
+
+    let (mut view, _) = view_for_markdown_with_flags("Hello", ViewFlags::EDITABLE);
+    assert_eq!(view.insert_text(&TextLocation::new(3, 100), "!"), EventResult::DocumentEdited);
+    assert_eq!(view.delete_range(TextLocation::new(0, 100)..TextLocation::new(0, 4)),
+               EventResult::DocumentEdited);
+    view.select_all();
+    assert_eq!(view.copy_selected_text(), Some("Hell!".to_owned()));
+}
+
+#[test]
+fn empty_documents_can_be_navigated_and_edited() {
+    let (mut view, _) = view_for_markdown_with_flags("", ViewFlags::EDITABLE);
+    view.key_down(Key::WordRight, Modifiers::SHIFT);
+    view.key_down(Key::WordLeft, Modifiers::empty());
+    view.delete_range(TextLocation::new(0, 0)..TextLocation::new(0, 5));
+    assert!(!view.can_undo());
+    view.insert_text(&TextLocation::new(2, 3), "Hi");
+    assert!(view.can_undo());
+}
+
+#[test]
+fn shift_arrows_extend_selection() {
+    let (mut view, _) = view_for_markdown("Hello world.");
+    view.key_down(Key::WordRight, Modifiers::SHIFT);
+    assert_eq!(view.copy_selected_text(), Some("Hello".to_owned()));
+    view.key_down(Key::Right, Modifiers::SHIFT);
+    assert_eq!(view.copy_selected_text(), Some("Hello ".to_owned()));
+    view.key_down(Key::Left, Modifiers::empty());
+    assert_eq!(view.copy_selected_text(), Some("".to_owned()));
+}
+
+#[test]
+fn word_left_crosses_paragraphs() {
+    let (mut view, _) = view_for_markdown("Hello\n\nworld");
+    view.key_down(Key::DocumentEnd, Modifiers::empty());
+    view.key_down(Key::WordLeft, Modifiers::SHIFT);
+    view.key_down(Key::WordLeft, Modifiers::SHIFT);
+    assert_eq!(view.copy_selected_text(), Some("\nworld".to_owned()));
+}
+
+#[test]
+fn line_end_keeps_the_last_character_of_lines_without_spaces() {
+    let text: String = (0..100).map(|_| '\u{4f60}').collect();
+    let (mut view, _) = view_for_markdown(&text);
+    view.key_down(Key::DocumentStart, Modifiers::empty());
+    let line_start_rect = view.caret_rect().unwrap();
+    view.key_down(Key::LineEnd, Modifiers::SHIFT);
+    let to_line_end = view.copy_selected_text().unwrap();
+    assert!(!to_line_end.is_empty() && to_line_end.len() < text.len());
+
+    // The line wraps between two ideographs, so its end is where the next line starts. The caret
+    // should still be drawn on the first line, and pressing End again shouldn't move it.
+    let line_end_rect = view.caret_rect().unwrap();
+    assert_eq!(line_end_rect.origin.y, line_start_rect.origin.y);
+    assert!(line_end_rect.origin.x > line_start_rect.origin.x);
+    view.key_down(Key::LineEnd, Modifiers::SHIFT);
+    assert_eq!(view.copy_selected_text(), Some(to_line_end.clone()));
+
+    // Moving down from the start lands on the same location, but at the start of the next line.
+    view.key_down(Key::DocumentStart, Modifiers::empty());
+    view.key_down(Key::Down, Modifiers::SHIFT);
+    assert_eq!(view.copy_selected_text(), Some(to_line_end));
+    assert!(view.caret_rect().unwrap().origin.y > line_start_rect.origin.y);
+}
+
+#[test]
+fn marked_text_is_replaced_then_committed() {
+    let (mut view, _) = view_for_markdown_with_flags("Hello.", ViewFlags::EDITABLE);
+    view.key_down(Key::LineEnd, Modifiers::empty());
+    view.key_down(Key::Left, Modifiers::empty());
+    view.set_marked_text("n", 1..1);
+    view.set_marked_text("ni", 2..2);
+    assert!(view.has_marked_text());
+    view.replace_selection("\u{4f60}");
+    assert!(!view.has_marked_text());
+    view.select_all();
+    assert_eq!(view.copy_selected_text(), Some("Hello\u{4f60}.".to_owned()));
+}
+
+#[test]
+fn read_only_views_ignore_marked_text() {
+    let (mut view, _) = view_for_markdown("Hello.");
+    view.key_down(Key::LineEnd, Modifiers::empty());
+    assert_eq!(view.set_marked_text("ni", 2..2), EventResult::None);
+    assert!(!view.has_marked_text());
+    view.key_down(Key::LineStart, Modifiers::SHIFT);
+    assert_eq!(view.copy_selected_text(), Some("Hello.".to_owned()));
+}
+
+#[test]
+fn moving_the_caret_commits_marked_text() {
+    let (mut view, _) = view_for_markdown_with_flags("Hello.", ViewFlags::EDITABLE);
+    view.key_down(Key::LineEnd, Modifiers::empty());
+    view.set_marked_text("ni", 2..2);
+    view.key_down(Key::Left, Modifiers::empty());
+    assert!(!view.has_marked_text());
+
+    view.key_down(Key::DocumentEnd, Modifiers::empty());
+    view.set_marked_text("ni", 2..2);
+    view.mouse_down(&LayoutPoint::new(2.0, 2.0), MouseEventKind::Left, Modifiers::empty());
+    assert!(!view.has_marked_text());
+    view.select_all();
+    assert_eq!(view.copy_selected_text(), Some("Hello.nini".to_owned()));
+}
+
+#[test]
+fn undo_reverts_typing_as_a_group() {
+    let (mut view, _) = view_for_markdown_with_flags("Hello", ViewFlags::EDITABLE);
+    view.key_down(Key::DocumentEnd, Modifiers::empty());
+    view.replace_selection(" ");
+    view.replace_selection("world");
+    view.delete_backward();
+    assert!(view.can_undo());
+
+    view.undo();
+    assert!(!view.can_undo());
+    assert_eq!(view.copy_selected_text(), Some("".to_owned()));
+    view.select_all();
+    assert_eq!(view.copy_selected_text(), Some("Hello".to_owned()));
+
+    view.redo();
+    assert!(!view.can_redo());
+    view.select_all();
+    assert_eq!(view.copy_selected_text(), Some("Hello worl".to_owned()));
+}
+
+#[test]
+fn undo_reverts_typing_and_backspacing_as_a_group() {
+    let (mut view, _) = view_for_markdown_with_flags("Hello", ViewFlags::EDITABLE);
+    view.key_down(Key::DocumentEnd, Modifiers::empty());
+    view.replace_selection("a");
+    view.replace_selection("b");
+    view.replace_selection("c");
+    view.delete_backward();
+    view.delete_backward();
+    view.delete_backward();
+
+    view.undo();
+    assert!(!view.can_undo());
+    view.select_all();
+    assert_eq!(view.copy_selected_text(), Some("Hello".to_owned()));
+
+    // Backspacing past what was typed starts a new group.
+    view.key_down(Key::DocumentEnd, Modifiers::empty());
+    view.replace_selection("!");
+    view.delete_backward();
+    view.delete_backward();
+    view.undo();
+    view.select_all();
+    assert_eq!(view.copy_selected_text(), Some("Hello".to_owned()));
+    assert!(view.can_undo());
+}
+
+#[test]
+fn undoing_a_deletion_restores_links_and_formatting() {
+    let (mut view, _) = view_for_markdown_with_flags("[Hello](https://example.com/) **world**.",
+                                                     ViewFlags::EDITABLE);
+    view.select_all();
+    let display_list_before = view.dump_display_list();
+    view.delete_backward();
+    assert_eq!(view.inspect_point(&LayoutPoint::new(2.0, 2.0)).url, None);
+
+    // Undo restores the selection too, so the bold run and the link's color and underline
+    // should be drawn exactly as before.
+    view.undo();
+    assert_eq!(view.copy_selected_text(), Some("Hello world.".to_owned()));
+    assert_eq!(view.inspect_point(&LayoutPoint::new(2.0, 2.0)).url,
+               Some("https://example.com/".to_owned()));
+    assert_eq!(view.dump_display_list(), display_list_before);
+}
+
+#[test]
+fn find_honors_options() {
+    let (mut view, _) = view_for_markdown("The cat scattered.\n\nCat.");
+    assert_eq!(view.find("cat", FindOptions::empty()).len(), 3);
+    assert_eq!(view.find("cat", FindOptions::CASE_SENSITIVE).len(), 2);
+    assert_eq!(view.find("cat", FindOptions::WHOLE_WORD).len(), 2);
+    assert_eq!(view.find("c.t", FindOptions::REGEX | FindOptions::CASE_SENSITIVE),
+               &[TextLocation::new(0, 4)..TextLocation::new(0, 7),
+                 TextLocation::new(0, 9)..TextLocation::new(0, 12)]);
+}
+
+#[test]
+fn find_next_wraps_around() {
+    let (mut view, _) = view_for_markdown("one two one");
+    view.find("one", FindOptions::empty());
+    assert_eq!(view.current_find_match(), Some(0));
+    assert_eq!(view.find_next(), Some(TextLocation::new(0, 8)..TextLocation::new(0, 11)));
+    assert_eq!(view.find_next(), Some(TextLocation::new(0, 0)..TextLocation::new(0, 3)));
+    assert_eq!(view.find_previous(), Some(TextLocation::new(0, 8)..TextLocation::new(0, 11)));
+}
+
+#[test]
+fn highlights_follow_edits() {
+    let (mut view, _) = view_for_markdown_with_flags("Hello world.", ViewFlags::EDITABLE);
+    let id = view.add_highlight(Highlight {
+        range: TextLocation::new(0, 6)..TextLocation::new(0, 11),
+        color: Color::new(255, 255, 0, 255),
+        style: HighlightStyle::Background,
+        z_index: 0,
+    });
+    view.insert_text(&TextLocation::new(0, 0), "Oh,\n");
+    assert_eq!(view.highlight(id).unwrap().range,
+               TextLocation::new(1, 6)..TextLocation::new(1, 11));
+    view.undo();
+    assert_eq!(view.highlight(id).unwrap().range,
+               TextLocation::new(0, 6)..TextLocation::new(0, 11));
+    assert!(view.remove_highlight(id).is_some());
+    assert!(view.highlight(id).is_none());
+}
+
+#[test]
+fn text_inserted_at_a_highlight_start_is_not_highlighted() {
+    let (mut view, _) = view_for_markdown_with_flags("Hello world.", ViewFlags::EDITABLE);
+    let id = view.add_highlight(Highlight {
+        range: TextLocation::new(0, 6)..TextLocation::new(0, 11),
+        color: Color::new(255, 255, 0, 255),
+        style: HighlightStyle::Background,
+        z_index: 0,
+    });
+    view.insert_text(&TextLocation::new(0, 6), "big ");
+    assert_eq!(view.highlight(id).unwrap().range,
+               TextLocation::new(0, 10)..TextLocation::new(0, 15));
+    view.insert_text(&TextLocation::new(0, 15), "s");
+    assert_eq!(view.highlight(id).unwrap().range,
+               TextLocation::new(0, 10)..TextLocation::new(0, 15));
+}
+
+#[test]
+fn wavy_highlights_draw_wavy_lines() {
+    let (mut view, _) = view_for_markdown("Helo world.");
+    view.add_highlight(Highlight {
+        range: TextLocation::new(0, 0)..TextLocation::new(0, 4),
+        color: Color::new(255, 0, 0, 255),
+        style: HighlightStyle::WavyUnderline,
+        z_index: 0,
+    });
+    assert!(view.dump_display_list().contains("\"style\": \"Wavy\""));
+}
+
+#[test]
+fn theme_paints_page_background() {
+    let (mut view, _) = view_for_markdown("Hello.");
+    assert!(!view.dump_display_list().contains("\"type\": \"rect\""));
+
+    let mut theme = Theme::default();
+    theme.page_background_color = Color::new(0, 0, 0, 255);
+    view.set_theme(theme);
+    assert!(view.dump_display_list()
+                .contains("\"type\": \"rect\", \"color\": [0.000, 0.000, 0.000, 1.000]"));
+}
+
+#[test]
+fn content_insets_pad_the_layout() {
+    let (mut view, _) = view_for_markdown("Hello.");
+    let height = view.layout_size().height;
+    view.set_content_insets(&TypedSideOffsets2D::new(10.0, 0.0, 20.0, 0.0));
+    assert!((view.layout_size().height - (height + 30.0)).abs() < 0.01);
+}
+
+#[test]
+fn unfocused_views_use_inactive_selection_color() {
+    let (mut view, _) = view_for_markdown("Hello.");
+    let mut theme = Theme::default();
+    theme.selection_background_color = Color::new(255, 0, 0, 255);
+    theme.inactive_selection_background_color = Color::new(0, 0, 255, 255);
+    view.set_theme(theme);
+    view.select_all();
+    assert!(view.dump_display_list().contains("[1.000, 0.000, 0.000, 1.000]"));
+
+    view.set_focused(false);
+    let display_list = view.dump_display_list();
+    assert!(!display_list.contains("[1.000, 0.000, 0.000, 1.000]"));
+    assert!(display_list.contains("[0.000, 0.000, 1.000, 1.000]"));
+}
+
+fn view_with_format_on_first_word(markdown: &str, format: Format) -> View {
+    let (mut view, _) = view_for_markdown_with_flags(markdown, ViewFlags::EDITABLE);
+    view.add_format(TextLocation::new(0, 0)..TextLocation::new(0, 5), format);
+    view
+}
+
+#[test]
+fn decorations_draw_lines_in_their_own_colors() {
+    let red = Color::new(255, 0, 0, 255);
+    let view = view_with_format_on_first_word("Hello world.",
+                                              Format::Decoration(Decoration::Strikethrough,
+                                                                 Some(red)));
+    let display_list = view.dump_display_list();
+    assert_eq!(display_list.matches("\"type\": \"line\"").count(), 1);
+    assert!(display_list.contains("\"style\": \"Solid\""));
+    assert!(display_list.contains("\"color\": [1.000, 0.000, 0.000, 1.000]"));
+
+    let view = view_with_format_on_first_word("Hello world.",
+                                              Format::Decoration(Decoration::DoubleUnderline,
+                                                                 None));
+    assert_eq!(view.dump_display_list().matches("\"type\": \"line\"").count(), 2);
+}
+
+#[test]
+fn run_backgrounds_are_drawn_beneath_the_selection() {
+    let green = Color::new(0, 255, 0, 255);
+    let mut view = view_with_format_on_first_word("Hello world.", Format::BackgroundColor(green));
+    let mut theme = Theme::default();
+    theme.selection_background_color = Color::new(255, 0, 0, 255);
+    view.set_theme(theme);
+    view.select_all();
+
+    let display_list = view.dump_display_list();
+    let background = "\"type\": \"rect\", \"color\": [0.000, 1.000, 0.000, 1.000]";
+    let selection = "\"type\": \"rect\", \"color\": [1.000, 0.000, 0.000, 1.000]";
+    let text = "\"type\": \"text\"";
+    assert!(display_list.find(background).unwrap() < display_list.find(selection).unwrap());
+    assert!(display_list.find(selection).unwrap() < display_list.find(text).unwrap());
+}
+
+#[test]
+fn shadows_wrap_text_and_are_not_clipped_to_the_line() {
+    let shadow = Shadow {
+        offset: Vector2D::new(4.0, 3.0),
+        color: Color::new(0, 0, 0, 128),
+        blur_radius: 2.0,
+    };
+    let view = view_with_format_on_first_word("Hello world.", Format::Shadow(shadow));
+    let display_list = view.dump_display_list();
+    let push_shadow = display_list.lines().find(|line| line.contains("push_shadow")).unwrap();
+    assert!(push_shadow.contains("\"offset\": [4.00, 3.00], \"blur_radius\": 2.00"));
+    assert_eq!(display_list.matches("pop_all_shadows").count(), 1);
+
+    let bounds = rect_in_dump_line(push_shadow, "bounds");
+    let clip = rect_in_dump_line(push_shadow, "clip");
+    assert!((clip[0] - (bounds[0] - 6.0)).abs() < 0.01);
+    assert!((clip[1] - (bounds[1] - 5.0)).abs() < 0.01);
+    assert!((clip[2] - (bounds[2] + 12.0)).abs() < 0.01);
+    assert!((clip[3] - (bounds[3] + 10.0)).abs() < 0.01);
+}
+
+fn rect_in_dump_line(line: &str, field: &str) -> Vec<f32> {
+    let start = line.find(&format!("\"{}\": [", field)).unwrap() + field.len() + 5;
+    let end = start + line[start..].find(']').unwrap();
+    line[start..end].split(", ").map(|value| value.parse().unwrap()).collect()
+}